@@ -0,0 +1,118 @@
+// the spatial grid against testing every bug, over the same bugs and the same web
+//   cargo bench --bench collision
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+use stupid_spooder_game::{
+    collision::{Collider, SpatialGrid},
+    definitions::{Definitions, EnemyDefinitions},
+    entities::{Web, PLAYFIELD},
+    game::GRID_CELL_SIZE,
+};
+
+const BUG_COUNTS: [usize; 3] = [100, 1_000, 5_000];
+const ITERATIONS: u32 = 200;
+
+struct Scene {
+    bugs: Vec<(Entity, Collider, Vec2)>,
+    spider: (Collider, Vec2),
+    threads: Vec<Collider>,
+}
+
+fn scene(count: usize) -> Scene {
+    let definitions = EnemyDefinitions::bundled();
+    let mut rng = StdRng::seed_from_u64(count as u64);
+    let bugs = (0..count)
+        .map(|index| {
+            let definition = &definitions.enemies[rng.gen_range(0..definitions.enemies.len())];
+            let position = Vec2::new(
+                rng.gen_range(-PLAYFIELD.x / 2.0..PLAYFIELD.x / 2.),
+                rng.gen_range(-PLAYFIELD.y / 2.0..PLAYFIELD.y / 2.),
+            );
+            (
+                Entity::from_raw(index as u32),
+                definition.collider.clone(),
+                position,
+            )
+        })
+        .collect();
+    let web = Web::new(Vec2::new(0., PLAYFIELD.y / 2.), Vec2::ZERO);
+    Scene {
+        bugs,
+        spider: (Collider::circle(20., Vec2::new(0., 4.)), Vec2::ZERO),
+        threads: web.segments().collect(),
+    }
+}
+
+fn touches(scene: &Scene, collider: &Collider, position: Vec2) -> bool {
+    let (spider, spider_pos) = &scene.spider;
+    spider.contact(*spider_pos, collider, position).is_some()
+        || scene
+            .threads
+            .iter()
+            .any(|thread| thread.contact(Vec2::ZERO, collider, position).is_some())
+}
+
+fn brute_force(scene: &Scene) -> usize {
+    scene
+        .bugs
+        .iter()
+        .filter(|(_, collider, position)| touches(scene, collider, *position))
+        .count()
+}
+
+// rebuilt every time, like the game does every frame
+fn grid(scene: &Scene, grid: &mut SpatialGrid, nearby: &mut Vec<Entity>) -> usize {
+    grid.clear();
+    for (entity, collider, position) in &scene.bugs {
+        grid.insert(*entity, collider.bounds(*position));
+    }
+    nearby.clear();
+    let (spider, spider_pos) = &scene.spider;
+    grid.query(spider.bounds(*spider_pos), nearby);
+    for thread in &scene.threads {
+        grid.query(thread.bounds(Vec2::ZERO), nearby);
+    }
+    // entities were handed out in order, so they index straight into the bugs
+    nearby
+        .iter()
+        .map(|entity| &scene.bugs[entity.index() as usize])
+        .filter(|(_, collider, position)| touches(scene, collider, *position))
+        .count()
+}
+
+fn time(mut pass: impl FnMut() -> usize) -> (Duration, usize) {
+    let hits = pass();
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(pass());
+    }
+    (started.elapsed() / ITERATIONS, hits)
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>14} {:>14} {:>8}",
+        "bugs", "brute force", "grid", "speedup"
+    );
+    for count in BUG_COUNTS {
+        let scene = scene(count);
+        let mut spatial_grid = SpatialGrid::new(GRID_CELL_SIZE);
+        let mut nearby = Vec::new();
+        let (brute_time, brute_hits) = time(|| brute_force(black_box(&scene)));
+        let (grid_time, grid_hits) =
+            time(|| grid(black_box(&scene), &mut spatial_grid, &mut nearby));
+        // the grid only ever skips bugs that can't touch anything
+        assert_eq!(brute_hits, grid_hits, "grid and brute force disagree");
+        println!(
+            "{:>6} {:>12.3}ms {:>12.3}ms {:>7.1}x",
+            count,
+            brute_time.as_secs_f64() * 1000.,
+            grid_time.as_secs_f64() * 1000.,
+            brute_time.as_secs_f64() / grid_time.as_secs_f64()
+        );
+    }
+}
@@ -4,4 +4,4 @@ cargo build --release --target wasm32-unknown-unknown
 # generate deployable wasm
 wasm-bindgen --no-typescript --target web --out-dir ./site/public/ --out-name "stupid-spider-game" ./target/wasm32-unknown-unknown/release/learning-bevy.wasm
 # copy assets into site
-cp ./assets/**/*.{png,mp3,ttf} ./site/public --parents
+cp ./assets/**/*.{png,mp3,wav,ttf,ron} ./site/public --parents
@@ -0,0 +1,98 @@
+// a throwaway leaderboard server for trying out the rest client locally
+//   cargo run --example leaderboard_stub -- 8080
+//   cargo run -- --leaderboard http://127.0.0.1:8080 --player me
+// runs are kept in memory and only sorted by score, nothing gets verified
+use serde_json::Value;
+use std::{
+    cmp::Reverse,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+};
+
+fn main() -> io::Result<()> {
+    let port = std::env::args().nth(1).unwrap_or_else(|| "8080".into());
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port))?;
+    println!("leaderboard stub listening on http://127.0.0.1:{}", port);
+    serve(listener)
+}
+
+// also started by the leaderboard tests, on whatever port is free
+pub fn serve(listener: TcpListener) -> io::Result<()> {
+    let mut runs: Vec<Value> = Vec::new();
+    for stream in listener.incoming() {
+        if let Err(err) = stream.and_then(|stream| handle(stream, &mut runs)) {
+            eprintln!("request failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+fn handle(mut stream: TcpStream, runs: &mut Vec<Value>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    let mut parts = request_line.split_whitespace();
+    let (status, response) = match (parts.next(), parts.next()) {
+        // the browser build asks before posting json
+        (Some("OPTIONS"), _) => ("204 No Content", String::new()),
+        (Some("POST"), Some("/runs")) => match serde_json::from_slice::<Value>(&body) {
+            Ok(run) => {
+                println!("run submitted: {}", run);
+                runs.push(run);
+                runs.sort_by_key(|run| Reverse(run["score"].as_u64().unwrap_or(0)));
+                ("201 Created", String::new())
+            }
+            Err(err) => ("400 Bad Request", err.to_string()),
+        },
+        (Some("GET"), Some(path)) if path.starts_with("/runs") => {
+            let query = path.split_once('?').map_or("", |(_, query)| query);
+            let param = |name: &str| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+            };
+            let limit = param("limit")
+                .and_then(|limit| limit.parse().ok())
+                .unwrap_or(runs.len());
+            // runs from before there were modes count as endless
+            let mode = param("mode").unwrap_or("endless");
+            let top: Vec<Value> = runs
+                .iter()
+                .filter(|run| run["mode"].as_str().unwrap_or("endless") == mode)
+                .take(limit)
+                .cloned()
+                .collect();
+            ("200 OK", Value::from(top).to_string())
+        }
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    )
+}
@@ -6,7 +6,7 @@ cargo build --target=x86_64-pc-windows-gnu --release
 #move releases to release folder
 cp ./target/release/stupid-spooder-game ./release/stupid-spooder-game
 cp ./target/x86_64-pc-windows-gnu/release/stupid-spooder-game.exe ./release/stupid-spooder-game.exe
-cp ./assets/**/*.{png,mp3,ttf} ./release/ -r --parents
+cp ./assets/**/*.{png,mp3,wav,ttf,ron} ./release/ -r --parents
 # zip releases
 cd ./release
 zip stupid-spooder-game-windows.zip stupid-spooder-game.exe assets -r
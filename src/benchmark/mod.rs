@@ -0,0 +1,149 @@
+use crate::{
+    definitions::EnemyDefinitions,
+    entities::{
+        lash_frog_tongues, move_enemies, move_web, EnemyEntity, EnemyEscaped, EnemyType, Frog,
+        GameEntity,
+    },
+    game::{
+        detect_intersection_player, rebuild_spatial_grid, GameResources, GameRng, GameState,
+        MAX_PLAYERS,
+    },
+};
+use bevy::{ecs::schedule::ExecutorKind, prelude::*};
+use rand::Rng;
+use std::time::{Duration, Instant};
+// two seconds at the headless timestep, over before any frog gets to lash
+pub const BENCHMARK_FRAMES: u32 = 120;
+// bugs start away from the thread and fly away from it so the spider lives through the run
+const BENCHMARK_CLEARING: f32 = 200.;
+#[derive(Resource)]
+struct Benchmark {
+    enemies: usize,
+    spawned: bool,
+    reported: bool,
+    frames: u32,
+    started: Option<Instant>,
+    total: Duration,
+    worst: Duration,
+}
+pub struct BenchmarkPlugin {
+    pub enemies: usize,
+}
+
+impl Plugin for BenchmarkPlugin {
+    fn build(&self, app: &mut App) {
+        // with a single thread nothing else runs while the collision pass is timed
+        app.edit_schedule(Update, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        })
+        .insert_resource(Benchmark {
+            enemies: self.enemies,
+            spawned: false,
+            reported: false,
+            frames: 0,
+            started: None,
+            total: Duration::ZERO,
+            worst: Duration::ZERO,
+        })
+        .add_systems(
+            Update,
+            start_collision_timer
+                .after(move_enemies)
+                .after(lash_frog_tongues)
+                .after(move_web)
+                .before(rebuild_spatial_grid),
+        )
+        .add_systems(
+            Update,
+            stop_collision_timer.after(detect_intersection_player),
+        )
+        // spawning lands after the timed pass, the first frame doesn't pay for it
+        .add_systems(Update, spawn_benchmark_enemies.after(stop_collision_timer));
+    }
+}
+
+fn spawn_benchmark_enemy(
+    commands: &mut Commands,
+    rng: &mut GameRng,
+    definitions: &EnemyDefinitions,
+) {
+    let definition = rng.gen_range(0..definitions.enemies.len());
+    let enemy = &definitions.enemies[definition];
+    let side: f32 = if rng.gen::<bool>() { 1. } else { -1. };
+    let x = side * rng.gen_range(BENCHMARK_CLEARING..640.);
+    let y = rng.gen_range(-360.0..360.);
+    let position = Vec2::new(x, y);
+    let mut enemy_entity = commands.spawn((
+        GameEntity,
+        enemy.collider.clone(),
+        EnemyEntity::new(definition, enemy.enemy_type, position, x < 0.),
+        Transform::from_translation(position.extend(2.)),
+    ));
+    if enemy.enemy_type == EnemyType::FROG {
+        enemy_entity.insert(Frog::default());
+    }
+}
+
+// bugs that escape are replaced so the count holds for the whole run
+fn spawn_benchmark_enemies(
+    mut commands: Commands,
+    mut benchmark: ResMut<Benchmark>,
+    mut rng: ResMut<GameRng>,
+    definitions: Res<EnemyDefinitions>,
+    mut escaped: EventReader<EnemyEscaped>,
+    mut game_resources: ResMut<GameResources>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() != GameState::Active || definitions.enemies.is_empty() {
+        return;
+    }
+    let count = if benchmark.spawned {
+        escaped.read().count()
+    } else {
+        benchmark.spawned = true;
+        info!("benchmark spawning {} bugs", benchmark.enemies);
+        benchmark.enemies
+    };
+    for _ in 0..count {
+        spawn_benchmark_enemy(&mut commands, &mut rng, &definitions);
+    }
+    // escape penalties would end the run early
+    game_resources.energy = [1.; MAX_PLAYERS];
+}
+
+fn start_collision_timer(mut benchmark: ResMut<Benchmark>) {
+    if benchmark.spawned {
+        benchmark.started = Some(Instant::now());
+    }
+}
+
+fn stop_collision_timer(
+    mut benchmark: ResMut<Benchmark>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    let Some(started) = benchmark.started.take() else {
+        return;
+    };
+    if benchmark.reported {
+        return;
+    }
+    if *game_state.get() == GameState::Active {
+        let elapsed = started.elapsed();
+        benchmark.frames += 1;
+        benchmark.total += elapsed;
+        benchmark.worst = benchmark.worst.max(elapsed);
+        if benchmark.frames < BENCHMARK_FRAMES {
+            return;
+        }
+        next_game_state.set(GameState::GameOver);
+    }
+    benchmark.reported = true;
+    info!(
+        "collision pass over {} bugs: {:.3}ms average, {:.3}ms worst across {} frames",
+        benchmark.enemies,
+        benchmark.total.as_secs_f64() * 1000. / benchmark.frames.max(1) as f64,
+        benchmark.worst.as_secs_f64() * 1000.,
+        benchmark.frames
+    );
+}
@@ -0,0 +1,334 @@
+use crate::{
+    collision::Collider,
+    definitions::{BossDefinitions, EnemyDefinitions},
+    entities::{
+        animation, move_web, spawn_enemy, GameEntity, PlayerAttached, PowerUpKind, Spider,
+        SpiderFilter, SpriteAssets, Web, PLAYFIELD,
+    },
+    game::{
+        DeathCause, GameResources, GameRng, GameState, PlayerSetup, PowerUpEffects, BOSS_HOVER_Y,
+        BOSS_INVULNERABILITY, HIT_FROM_BELOW,
+    },
+};
+use bevy::prelude::*;
+use rand::Rng;
+// which bosses were already fought this run and which one is on screen
+#[derive(Resource, Default)]
+pub struct BossFight {
+    pub active: Option<usize>,
+    next: usize,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BossState {
+    // patrolling side to side, waiting for the next attack
+    Hover,
+    Dive { target: Vec2 },
+    // flying back up to the patrol height, also how a boss enters
+    Return,
+}
+#[derive(Component)]
+pub struct Boss {
+    // index into BossDefinitions
+    pub definition: usize,
+    pub health: u32,
+    pub state: BossState,
+    // where the boss was before its last move, for swept collision tests
+    pub previous: Vec2,
+    heading: f32,
+    invulnerable: f32,
+    dive_timer: f32,
+    summon_timer: f32,
+}
+impl Boss {
+    pub fn new(definition: usize, health: u32, position: Vec2) -> Self {
+        Self {
+            definition,
+            health,
+            state: BossState::Return,
+            previous: position,
+            heading: 1.,
+            invulnerable: 0.,
+            dive_timer: 0.,
+            summon_timer: 0.,
+        }
+    }
+}
+pub struct BossesPlugin;
+
+impl Plugin for BossesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BossFight>()
+            .add_systems(Update, reset_boss_fight)
+            .add_systems(Update, start_boss_fight)
+            .add_systems(Update, move_bosses)
+            .add_systems(Update, hit_bosses.after(move_bosses).after(move_web));
+    }
+}
+
+fn reset_boss_fight(mut boss_fight: ResMut<BossFight>, game_state: Res<State<GameState>>) {
+    if *game_state.get() == GameState::Init {
+        *boss_fight = BossFight::default();
+    }
+}
+
+// the next boss shows up once the score reaches its threshold, never two at once
+fn start_boss_fight(
+    mut commands: Commands,
+    mut sprite_assets: SpriteAssets,
+    mut boss_fight: ResMut<BossFight>,
+    definitions: Res<BossDefinitions>,
+    game_resources: Res<GameResources>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() != GameState::Active || boss_fight.active.is_some() {
+        return;
+    }
+    let index = boss_fight.next;
+    let Some(boss) = definitions.bosses.get(index) else {
+        return;
+    };
+    if game_resources.score < boss.score {
+        return;
+    }
+    info!("boss incoming: {}", boss.name);
+    boss_fight.active = Some(index);
+    boss_fight.next += 1;
+    let size = boss.size();
+    let position = Vec2::new(0., PLAYFIELD.y / 2. + size.y * 2.);
+    let (animation_indices, animation_timer) = animation(boss.frames, boss.animation_speed);
+    let boss_atlas_handle = sprite_assets.atlas(
+        boss.sprite.clone(),
+        size,
+        boss.frames,
+        Some(Vec2::splat(1.)),
+    );
+    commands.spawn((
+        GameEntity,
+        boss.collider.clone(),
+        Boss::new(index, boss.health, position),
+        SpriteSheetBundle {
+            texture_atlas: boss_atlas_handle,
+            transform: Transform::from_translation(position.extend(3.)),
+            sprite: TextureAtlasSprite {
+                custom_size: Some(size * 2.),
+                ..default()
+            },
+            ..default()
+        },
+        animation_indices,
+        animation_timer,
+    ));
+}
+
+// moves `from` towards `to` by at most `distance`, true once it got there
+fn approach(from: Vec2, to: Vec2, distance: f32) -> (Vec2, bool) {
+    let offset = to - from;
+    if offset.length() <= distance {
+        (to, true)
+    } else {
+        (from + offset.normalize() * distance, false)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn move_bosses(
+    mut commands: Commands,
+    mut boss_query: Query<(&mut Transform, &mut Boss, &mut TextureAtlasSprite)>,
+    web_query: Query<&Web, With<PlayerAttached>>,
+    definitions: Res<BossDefinitions>,
+    enemy_definitions: Res<EnemyDefinitions>,
+    mut sprite_assets: SpriteAssets,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() != GameState::Active {
+        return;
+    }
+    let delta = time.delta_seconds();
+    for (mut transform, mut boss, mut sprite) in &mut boss_query {
+        let Some(definition) = definitions.bosses.get(boss.definition) else {
+            continue;
+        };
+        let Some(phase) = definition.phase(boss.health) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        boss.previous = position;
+        boss.invulnerable = (boss.invulnerable - delta).max(0.);
+        boss.dive_timer += delta;
+        boss.summon_timer += delta;
+        let next = match boss.state {
+            BossState::Hover => {
+                let patrol = PLAYFIELD.x / 2. - definition.size().x;
+                if position.x.abs() >= patrol {
+                    boss.heading = -position.x.signum();
+                }
+                let (next, _) = approach(
+                    position,
+                    Vec2::new(
+                        position.x + boss.heading * phase.speed * delta,
+                        BOSS_HOVER_Y,
+                    ),
+                    phase.speed * delta,
+                );
+                // dives go for a point on one of the threads as it hangs right now
+                let webs: Vec<&Web> = web_query.iter().collect();
+                let target = phase
+                    .dive
+                    .as_ref()
+                    .filter(|dive| boss.dive_timer >= dive.interval && !webs.is_empty())
+                    .map(|_| webs[rng.gen_range(0..webs.len())])
+                    .map(|web| web.points[rng.gen_range(1..web.points.len())]);
+                if let Some(target) = target {
+                    boss.state = BossState::Dive { target };
+                    boss.dive_timer = 0.;
+                }
+                if let Some(summon) = phase
+                    .summon
+                    .as_ref()
+                    .filter(|summon| boss.summon_timer >= summon.interval)
+                {
+                    boss.summon_timer = 0.;
+                    let minion = enemy_definitions
+                        .enemies
+                        .iter()
+                        .position(|enemy| enemy.name == summon.minion);
+                    match minion {
+                        Some(minion) => {
+                            for _ in 0..summon.count {
+                                spawn_enemy(
+                                    &mut commands,
+                                    &mut sprite_assets,
+                                    &enemy_definitions,
+                                    minion,
+                                    position,
+                                    rng.gen(),
+                                );
+                            }
+                        }
+                        None => warn!("{} summons unknown bug {}", definition.name, summon.minion),
+                    }
+                }
+                next
+            }
+            BossState::Dive { target } => {
+                let speed = phase.dive.as_ref().map_or(phase.speed, |dive| dive.speed);
+                let (next, arrived) = approach(position, target, speed * delta);
+                if arrived {
+                    boss.state = BossState::Return;
+                }
+                next
+            }
+            BossState::Return => {
+                let (next, arrived) = approach(
+                    position,
+                    Vec2::new(position.x, BOSS_HOVER_Y),
+                    phase.speed * delta,
+                );
+                if arrived {
+                    boss.state = BossState::Hover;
+                    boss.dive_timer = 0.;
+                }
+                next
+            }
+        };
+        if next.x != position.x {
+            sprite.flip_x = next.x < position.x;
+        }
+        // flashes red while it can't be caught
+        sprite.color = if boss.invulnerable > 0. {
+            Color::rgb(1., 0.4, 0.4)
+        } else {
+            Color::WHITE
+        };
+        transform.translation.x = next.x;
+        transform.translation.y = next.y;
+    }
+}
+
+// catching the boss from anywhere but below wears it down, a dive through the thread snaps it
+#[allow(clippy::too_many_arguments)]
+pub fn hit_bosses(
+    mut commands: Commands,
+    mut boss_query: Query<(&Transform, &Collider, Entity, &mut Boss)>,
+    player_query: Query<(&Transform, &Collider, &Spider), SpiderFilter>,
+    web_query: Query<(&Web, &PlayerAttached, Entity)>,
+    definitions: Res<BossDefinitions>,
+    mut boss_fight: ResMut<BossFight>,
+    mut effects: ResMut<PowerUpEffects>,
+    mut game_resources: ResMut<GameResources>,
+    game_state: Res<State<GameState>>,
+    setup: Res<PlayerSetup>,
+) {
+    if *game_state.get() != GameState::Active {
+        return;
+    }
+    let name = |boss: &Boss| {
+        definitions
+            .bosses
+            .get(boss.definition)
+            .map_or("boss", |definition| definition.name.as_str())
+            .to_string()
+    };
+    for (web, attached, web_entity) in web_query.iter() {
+        let player_entity = attached.spider;
+        let Ok((player_transform, player_collider, spider)) = player_query.get(player_entity)
+        else {
+            continue;
+        };
+        let player_pos = player_transform.translation.truncate();
+        for (boss_transform, boss_collider, boss_entity, mut boss) in &mut boss_query {
+            let boss_pos = boss_transform.translation.truncate();
+            match player_collider.contact(player_pos, boss_collider, boss_pos) {
+                // the normal points from the spider to the boss
+                Some(contact) if contact.normal.y < HIT_FROM_BELOW => {
+                    commands.entity(player_entity).despawn();
+                    commands.entity(web_entity).despawn();
+                    game_resources.kill(DeathCause::HitFromBelow(name(&boss)));
+                    break;
+                }
+                Some(_contact) if boss.invulnerable <= 0. => {
+                    boss.health = boss.health.saturating_sub(1);
+                    boss.invulnerable = BOSS_INVULNERABILITY;
+                    boss.state = BossState::Return;
+                    if boss.health > 0 {
+                        continue;
+                    }
+                    commands.entity(boss_entity).despawn();
+                    boss_fight.active = None;
+                    if let Some(definition) = definitions.bosses.get(boss.definition) {
+                        info!("{} defeated", definition.name);
+                        game_resources.score += definition.reward_score;
+                        // the energy goes to whoever landed the last catch
+                        let pool = setup.energy_pool(spider.player);
+                        game_resources.energy[pool] =
+                            (game_resources.energy[pool] + definition.reward_energy).min(1.);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            let BossState::Dive { .. } = boss.state else {
+                continue;
+            };
+            let swept = boss_collider.swept(boss.previous, boss_pos);
+            if web
+                .segments()
+                .any(|thread| swept.contact(Vec2::ZERO, &thread, Vec2::ZERO).is_some())
+            {
+                // the shield turns the dive away instead
+                if effects.is_active(PowerUpKind::Shield) {
+                    effects.consume(PowerUpKind::Shield);
+                    game_resources.break_combo();
+                    boss.state = BossState::Return;
+                    continue;
+                }
+                commands.entity(web_entity).despawn();
+                commands.entity(player_entity).despawn();
+                game_resources.kill(DeathCause::SnappedThread(name(&boss)));
+                break;
+            }
+        }
+    }
+}
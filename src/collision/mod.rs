@@ -0,0 +1,270 @@
+use bevy::{prelude::*, utils::HashMap};
+use serde::Deserialize;
+// every shape is a convex core grown by a radius: a circle is a single point, a
+// capsule is a segment and a polygon is used as is
+#[derive(Deserialize, Clone, Debug)]
+pub enum Shape {
+    Circle {
+        radius: f32,
+    },
+    Capsule {
+        from: Vec2,
+        to: Vec2,
+        radius: f32,
+    },
+    Polygon {
+        points: Vec<Vec2>,
+        #[serde(default)]
+        radius: f32,
+    },
+}
+// the offset is relative to the entity's translation, independent of its sprite
+#[derive(Component, Deserialize, Clone, Debug)]
+pub struct Collider {
+    pub shape: Shape,
+    #[serde(default)]
+    pub offset: Vec2,
+}
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    // points from the first collider towards the second one
+    pub normal: Vec2,
+}
+impl Collider {
+    pub fn circle(radius: f32, offset: Vec2) -> Self {
+        Self {
+            shape: Shape::Circle { radius },
+            offset,
+        }
+    }
+    pub fn capsule(from: Vec2, to: Vec2, radius: f32) -> Self {
+        Self {
+            shape: Shape::Capsule { from, to, radius },
+            offset: Vec2::ZERO,
+        }
+    }
+    fn core(&self, position: Vec2) -> (Vec<Vec2>, f32) {
+        let origin = position + self.offset;
+        match &self.shape {
+            Shape::Circle { radius } => (vec![origin], *radius),
+            Shape::Capsule { from, to, radius } => (vec![origin + *from, origin + *to], *radius),
+            Shape::Polygon { points, radius } => {
+                (points.iter().map(|p| origin + *p).collect(), *radius)
+            }
+        }
+    }
+    // world space box around the shape, radius included
+    pub fn bounds(&self, position: Vec2) -> Rect {
+        let (points, radius) = self.core(position);
+        let (min, max) = points.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+        Rect::from_corners(min - radius, max + radius)
+    }
+    // the area covered while moving from one position to the other, in world space
+    pub fn swept(&self, from: Vec2, to: Vec2) -> Collider {
+        let (mut points, radius) = self.core(from);
+        points.extend(self.core(to).0);
+        let hull = convex_hull(points);
+        match hull.len() {
+            0 | 1 => Collider::circle(radius, hull.first().copied().unwrap_or(to)),
+            2 => Collider::capsule(hull[0], hull[1], radius),
+            _ => Collider {
+                shape: Shape::Polygon {
+                    points: hull,
+                    radius,
+                },
+                offset: Vec2::ZERO,
+            },
+        }
+    }
+    pub fn contact(
+        &self,
+        position: Vec2,
+        other: &Collider,
+        other_position: Vec2,
+    ) -> Option<Contact> {
+        let (a, a_radius) = self.core(position);
+        let (b, b_radius) = other.core(other_position);
+        let radius = a_radius + b_radius;
+        if let Some(normal) = overlap(&a, &b) {
+            return Some(Contact { normal });
+        }
+        let (closest_a, closest_b) = closest_points(&a, &b);
+        let distance = closest_a.distance(closest_b);
+        if distance >= radius {
+            return None;
+        }
+        Some(Contact {
+            normal: (closest_b - closest_a).try_normalize().unwrap_or(Vec2::Y),
+        })
+    }
+}
+
+// uniform grid over the playfield, each entity is listed in every cell its bounds touch
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+}
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+    // keeps the allocated cells around, the playfield doesn't change between frames
+    pub fn clear(&mut self) {
+        for entities in self.cells.values_mut() {
+            entities.clear();
+        }
+    }
+    fn cell_range(&self, bounds: Rect) -> (IVec2, IVec2) {
+        (
+            (bounds.min / self.cell_size).floor().as_ivec2(),
+            (bounds.max / self.cell_size).floor().as_ivec2(),
+        )
+    }
+    pub fn insert(&mut self, entity: Entity, bounds: Rect) {
+        let (min, max) = self.cell_range(bounds);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+    }
+    // every entity sharing a cell with the bounds, sorted so results don't depend on the hash order
+    pub fn query(&self, bounds: Rect, found: &mut Vec<Entity>) {
+        let (min, max) = self.cell_range(bounds);
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if let Some(entities) = self.cells.get(&IVec2::new(x, y)) {
+                    found.extend_from_slice(entities);
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+    }
+}
+
+// monotone chain, drops duplicated and collinear points
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() * 2);
+    // lower half left to right, then upper half right to left
+    for pass in 0..2 {
+        let start = hull.len();
+        for i in 0..points.len() {
+            let point = if pass == 0 {
+                points[i]
+            } else {
+                points[points.len() - 1 - i]
+            };
+            while hull.len() >= start + 2
+                && (hull[hull.len() - 1] - hull[hull.len() - 2])
+                    .perp_dot(point - hull[hull.len() - 2])
+                    <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+fn edges(points: &[Vec2]) -> Vec<(Vec2, Vec2)> {
+    match points.len() {
+        0 => vec![],
+        1 => vec![(points[0], points[0])],
+        2 => vec![(points[0], points[1])],
+        n => (0..n).map(|i| (points[i], points[(i + 1) % n])).collect(),
+    }
+}
+
+fn axes(points: &[Vec2]) -> Vec<Vec2> {
+    let mut axes: Vec<Vec2> = edges(points)
+        .iter()
+        .filter_map(|(a, b)| (*b - *a).perp().try_normalize())
+        .collect();
+    // a lone segment also needs its own direction to tell apart collinear cores
+    if points.len() == 2 {
+        axes.extend((points[1] - points[0]).try_normalize());
+    }
+    axes
+}
+
+fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+    points
+        .iter()
+        .map(|p| p.dot(axis))
+        .fold((f32::MAX, f32::MIN), |(min, max), d| {
+            (min.min(d), max.max(d))
+        })
+}
+
+fn center(points: &[Vec2]) -> Vec2 {
+    points.iter().copied().sum::<Vec2>() / points.len().max(1) as f32
+}
+
+// separating axis test between the cores, returns the axis of least penetration
+fn overlap(a: &[Vec2], b: &[Vec2]) -> Option<Vec2> {
+    let mut best: Option<(Vec2, f32)> = None;
+    for axis in axes(a).into_iter().chain(axes(b)) {
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        let depth = (a_max - b_min).min(b_max - a_min);
+        if depth <= 0. {
+            return None;
+        }
+        if best.map_or(true, |(_, best_depth)| depth < best_depth) {
+            best = Some((axis, depth));
+        }
+    }
+    let (axis, _) = best?;
+    if (center(b) - center(a)).dot(axis) < 0. {
+        Some(-axis)
+    } else {
+        Some(axis)
+    }
+}
+
+pub fn closest_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    if ab.length_squared() == 0. {
+        return a;
+    }
+    a + ab * ((point - a).dot(ab) / ab.length_squared()).clamp(0., 1.)
+}
+
+// for disjoint convex cores the closest pair is always a vertex against an edge
+fn closest_points(a: &[Vec2], b: &[Vec2]) -> (Vec2, Vec2) {
+    let mut best = (Vec2::ZERO, Vec2::ZERO, f32::MAX);
+    for (from, to) in edges(a) {
+        for point in b {
+            let closest = closest_on_segment(*point, from, to);
+            let distance = closest.distance_squared(*point);
+            if distance < best.2 {
+                best = (closest, *point, distance);
+            }
+        }
+    }
+    for (from, to) in edges(b) {
+        for point in a {
+            let closest = closest_on_segment(*point, from, to);
+            let distance = closest.distance_squared(*point);
+            if distance < best.2 {
+                best = (*point, closest, distance);
+            }
+        }
+    }
+    (best.0, best.1)
+}
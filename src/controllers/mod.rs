@@ -1,58 +1,378 @@
-use bevy::{
-    input::{
-        gamepad::{GamepadAxisChangedEvent, GamepadButtonChangedEvent},
-        keyboard::KeyboardInput,
-    },
-    prelude::*,
+use crate::{
+    entities::{Spider, SpiderFilter},
+    game::{PlayerSetup, MAX_PLAYERS},
+    replay::live_input,
+    storage,
 };
-#[derive(Resource)]
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+pub const GAMEPAD_BUTTON_HELD: f32 = 0.5;
+// closer to the cursor than this the spider eases off instead of overshooting, world units
+pub const MOUSE_FOLLOW_SLOWDOWN: f32 = 48.;
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Boost,
+    Pause,
+    Confirm,
+}
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Boost,
+        Action::Pause,
+        Action::Confirm,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveUp => "move up",
+            Action::MoveDown => "move down",
+            Action::MoveLeft => "move left",
+            Action::MoveRight => "move right",
+            Action::Boost => "boost",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActionBinding {
+    pub action: Action,
+    pub keys: Vec<KeyCode>,
+    pub buttons: Vec<GamepadButtonType>,
+}
+// shapes raw stick and trigger values before they reach the spider
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct InputProcessing {
+    // stick travel below this is ignored, mostly drift
+    pub deadzone: f32,
+    // travel past this counts as pushed all the way
+    pub outer_deadzone: f32,
+    // exponent on the travel left between the deadzones, above 1 is finer near the center
+    pub response_curve: f32,
+    pub trigger_deadzone: f32,
+    // keeps diagonals from keys or the d-pad as fast as straight lines
+    pub normalize_diagonals: bool,
+}
+impl Default for InputProcessing {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            outer_deadzone: 0.95,
+            response_curve: 1.5,
+            trigger_deadzone: 0.1,
+            normalize_diagonals: true,
+        }
+    }
+}
+impl InputProcessing {
+    // 0 up to the inner edge, 1 from the outer edge on, linear in between
+    fn live(value: f32, inner: f32, outer: f32) -> f32 {
+        ((value - inner) / (outer - inner).max(f32::EPSILON)).clamp(0., 1.)
+    }
+    // the deadzones are radial so the stick keeps its direction near the edges
+    pub fn stick(&self, raw: Vec2) -> Vec2 {
+        let travel = raw.length();
+        if travel <= self.deadzone {
+            return Vec2::ZERO;
+        }
+        let live = Self::live(travel, self.deadzone, self.outer_deadzone);
+        raw / travel * live.powf(self.response_curve)
+    }
+    pub fn trigger(&self, raw: f32) -> f32 {
+        Self::live(raw, self.trigger_deadzone, self.outer_deadzone)
+    }
+    pub fn movement(&self, movement: Vec2) -> Vec2 {
+        if self.normalize_diagonals && movement.length() > 1. {
+            movement.normalize()
+        } else {
+            movement
+        }
+    }
+}
+// how player one steers, the other schemes are always listened to as well
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlScheme {
+    // keys, gamepad sticks and touch
+    #[default]
+    Standard,
+    // the spider heads for the cursor and the left mouse button boosts, for one hand
+    MouseFollow,
+}
+impl ControlScheme {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ControlScheme::Standard => "standard",
+            ControlScheme::MouseFollow => "follow mouse",
+        }
+    }
+    pub fn toggle(&self) -> Self {
+        match self {
+            ControlScheme::Standard => ControlScheme::MouseFollow,
+            ControlScheme::MouseFollow => ControlScheme::Standard,
+        }
+    }
+}
+// which keys and gamepad buttons trigger each action, saved as "bindings" together with
+// the input processing settings and the control scheme
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct ActionMap {
+    pub bindings: Vec<ActionBinding>,
+    #[serde(default)]
+    pub processing: InputProcessing,
+    #[serde(default)]
+    pub scheme: ControlScheme,
+}
+impl Default for ActionMap {
+    fn default() -> Self {
+        let binding = |action, keys: &[KeyCode], buttons: &[GamepadButtonType]| ActionBinding {
+            action,
+            keys: keys.to_vec(),
+            buttons: buttons.to_vec(),
+        };
+        Self {
+            bindings: vec![
+                binding(
+                    Action::MoveUp,
+                    &[KeyCode::Up, KeyCode::W],
+                    &[GamepadButtonType::DPadUp],
+                ),
+                binding(
+                    Action::MoveDown,
+                    &[KeyCode::Down, KeyCode::S],
+                    &[GamepadButtonType::DPadDown],
+                ),
+                binding(
+                    Action::MoveLeft,
+                    &[KeyCode::Left, KeyCode::A],
+                    &[GamepadButtonType::DPadLeft],
+                ),
+                binding(
+                    Action::MoveRight,
+                    &[KeyCode::Right, KeyCode::D],
+                    &[GamepadButtonType::DPadRight],
+                ),
+                binding(
+                    Action::Boost,
+                    &[KeyCode::ShiftLeft, KeyCode::ShiftRight],
+                    &[GamepadButtonType::RightTrigger2],
+                ),
+                binding(
+                    Action::Pause,
+                    &[KeyCode::Escape, KeyCode::P],
+                    &[GamepadButtonType::Start],
+                ),
+                binding(
+                    Action::Confirm,
+                    &[KeyCode::Space, KeyCode::Return],
+                    &[GamepadButtonType::Start, GamepadButtonType::South],
+                ),
+            ],
+            processing: InputProcessing::default(),
+            scheme: ControlScheme::default(),
+        }
+    }
+}
+impl ActionMap {
+    const NAME: &'static str = "bindings";
+    // actions missing from an older file keep their default bindings
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        // bindings used to be saved with the game's data
+        let saved = match storage::read_config(Self::NAME)? {
+            Some(bindings) => Some(bindings),
+            None => storage::read(Self::NAME)?,
+        };
+        let mut map = match saved {
+            Some(bindings) => ron::from_str::<Self>(&bindings)?,
+            None => return Ok(Self::default()),
+        };
+        for binding in Self::default().bindings {
+            if map.binding(binding.action).is_none() {
+                map.bindings.push(binding);
+            }
+        }
+        Ok(map)
+    }
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        storage::write_config(Self::NAME, &ron::to_string(self)?)
+    }
+    pub fn binding(&self, action: Action) -> Option<&ActionBinding> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.action == action)
+    }
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.binding(action).map_or(&[], |binding| &binding.keys)
+    }
+    pub fn buttons(&self, action: Action) -> &[GamepadButtonType] {
+        self.binding(action).map_or(&[], |binding| &binding.buttons)
+    }
+    // a rebound key replaces the action's keys and moves over from whichever other movement
+    // or boost action had it, that one takes the old keys if it would be left without any.
+    // pause and confirm share keys with everything on purpose. false when there was nothing
+    // to swap and the rebind was refused
+    pub fn bind_key(&mut self, action: Action, key: KeyCode) -> bool {
+        Self::rebind(&mut self.bindings, action, key, |binding| &mut binding.keys)
+    }
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType) -> bool {
+        Self::rebind(&mut self.bindings, action, button, |binding| {
+            &mut binding.buttons
+        })
+    }
+    fn rebind<T: Copy + PartialEq>(
+        bindings: &mut [ActionBinding],
+        action: Action,
+        input: T,
+        inputs: fn(&mut ActionBinding) -> &mut Vec<T>,
+    ) -> bool {
+        let Some(index) = bindings.iter().position(|binding| binding.action == action) else {
+            return false;
+        };
+        let mut old = inputs(&mut bindings[index]).clone();
+        old.retain(|i| *i != input);
+        let conflicts = |binding: &ActionBinding| {
+            binding.action != action && Self::conflicts(action, binding.action)
+        };
+        let strands = bindings
+            .iter_mut()
+            .filter(|binding| conflicts(binding))
+            .any(|binding| inputs(binding).as_slice() == [input]);
+        if strands && old.is_empty() {
+            return false;
+        }
+        *inputs(&mut bindings[index]) = vec![input];
+        for binding in bindings.iter_mut().filter(|binding| conflicts(binding)) {
+            let taken = inputs(binding);
+            if taken.as_slice() == [input] {
+                *taken = old.clone();
+            } else {
+                taken.retain(|i| *i != input);
+            }
+        }
+        true
+    }
+    fn conflicts(a: Action, b: Action) -> bool {
+        let in_game = |action| !matches!(action, Action::Pause | Action::Confirm);
+        in_game(a) && in_game(b)
+    }
+}
+// action level view of the keyboard and every connected gamepad
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    pub map: ResMut<'w, ActionMap>,
+    keys: ResMut<'w, Input<KeyCode>>,
+    buttons: ResMut<'w, Input<GamepadButton>>,
+}
+impl ActionInput<'_> {
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.keys
+            .any_just_pressed(self.map.keys(action).iter().copied())
+            || self
+                .buttons
+                .get_just_pressed()
+                .any(|button| self.map.buttons(action).contains(&button.button_type))
+    }
+    // later systems won't see this press again in the same frame
+    pub fn consume(&mut self, action: Action) {
+        for key in self.map.keys(action) {
+            self.keys.reset(*key);
+        }
+        let buttons: Vec<GamepadButton> = self
+            .buttons
+            .get_just_pressed()
+            .filter(|button| self.map.buttons(action).contains(&button.button_type))
+            .copied()
+            .collect();
+        for button in buttons {
+            self.buttons.reset(button);
+        }
+    }
+    pub fn key_just_pressed(&self, key: KeyCode) -> bool {
+        self.keys.just_pressed(key)
+    }
+    pub fn button_just_pressed(&self, button: GamepadButtonType) -> bool {
+        self.buttons
+            .get_just_pressed()
+            .any(|pressed| pressed.button_type == button)
+    }
+    pub fn just_pressed_key(&self) -> Option<KeyCode> {
+        self.keys.get_just_pressed().next().copied()
+    }
+    pub fn just_pressed_button(&self) -> Option<GamepadButtonType> {
+        self.buttons
+            .get_just_pressed()
+            .next()
+            .map(|button| button.button_type)
+    }
+    pub fn consume_all(&mut self) {
+        self.keys.reset_all();
+        self.buttons.reset_all();
+    }
+}
+#[derive(Default)]
 pub struct PlayerControllerState {
     x: f32,
     y: f32,
-    boost: bool,
+    // 0 to 1, keys boost all the way and triggers as far as they are pressed
+    boost: f32,
+    // held movement actions, oldest first, so the latest press on an axis wins
+    held: Vec<Action>,
 }
 
 impl PlayerControllerState {
-    pub fn release_move_player(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Up => self.y = 0.0,
-            KeyCode::Down => self.y = 0.0,
-            KeyCode::Left => self.x = 0.0,
-            KeyCode::Right => self.x = 0.0,
-            KeyCode::ShiftLeft => self.boost = false,
+    // keeps the press order of everything still held, new presses go to the back
+    pub fn set_held(&mut self, action: Action, held: bool) {
+        let index = self.held.iter().position(|a| *a == action);
+        match (held, index) {
+            (true, None) => self.held.push(action),
+            (false, Some(index)) => {
+                self.held.remove(index);
+            }
             _ => (),
         }
     }
-    pub fn move_player(&mut self, key: KeyCode) {
-        match key {
-            KeyCode::Up => self.y = 1.0,
-            KeyCode::Down => self.y = -1.0,
-            KeyCode::Left => self.x = -1.0,
-            KeyCode::Right => self.x = 1.0,
-            KeyCode::ShiftLeft => self.boost = true,
-            _ => (),
-        }
+    // -1, 1 or nothing when neither direction is held
+    fn held_axis(&self, negative: Action, positive: Action) -> Option<f32> {
+        self.held.iter().rev().find_map(|action| {
+            if *action == negative {
+                Some(-1.)
+            } else if *action == positive {
+                Some(1.)
+            } else {
+                None
+            }
+        })
     }
-    pub fn move_player_joystick(&mut self, val: f32, axis: GamepadAxisType) {
-        match axis {
-            GamepadAxisType::LeftStickX => self.x = val,
-            GamepadAxisType::LeftStickY => self.y = val,
-            _ => (),
-        }
+    // a held key or button decides its axis, the stick only moves axes nobody holds
+    pub fn resolve(&mut self, stick: Vec2, boost: f32, processing: &InputProcessing) {
+        let movement = processing.movement(Vec2::new(
+            self.held_axis(Action::MoveLeft, Action::MoveRight)
+                .unwrap_or(stick.x),
+            self.held_axis(Action::MoveDown, Action::MoveUp)
+                .unwrap_or(stick.y),
+        ));
+        self.x = movement.x;
+        self.y = movement.y;
+        self.boost = boost;
     }
-    pub fn move_player_joystick_buttons(&mut self, val: f32, button: GamepadButtonType) {
-        match button {
-            GamepadButtonType::RightTrigger2 => {
-                if val > 0.5 {
-                    self.boost = true
-                } else {
-                    self.boost = false
-                }
-            }
-            _ => (),
-        }
+    pub fn set_state(&mut self, x: f32, y: f32, boost: f32) {
+        self.x = x;
+        self.y = y;
+        self.boost = boost;
     }
     pub fn is_boosting(&self) -> bool {
+        self.boost > 0.
+    }
+    pub fn boost(&self) -> f32 {
         self.boost
     }
     pub fn is_moving(&self) -> bool {
@@ -63,41 +383,307 @@ impl PlayerControllerState {
     }
 }
 
+// one state per player, player one first
+#[derive(Resource, Default)]
+pub struct PlayerControllers {
+    players: [PlayerControllerState; MAX_PLAYERS],
+}
+impl PlayerControllers {
+    pub fn get(&self, player: usize) -> &PlayerControllerState {
+        &self.players[player]
+    }
+    pub fn get_mut(&mut self, player: usize) -> &mut PlayerControllerState {
+        &mut self.players[player]
+    }
+}
+// the keyboard and gamepads, read as a whole every frame
+#[derive(SystemParam)]
+pub struct InputDevices<'w> {
+    keys: Res<'w, Input<KeyCode>>,
+    buttons: Res<'w, Axis<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+}
+impl InputDevices<'_> {
+    // in connection order so every player keeps their gamepad
+    pub fn gamepads(&self) -> Vec<Gamepad> {
+        let mut gamepads: Vec<Gamepad> = self.gamepads.iter().collect();
+        gamepads.sort_by_key(|gamepad| gamepad.id);
+        gamepads
+    }
+    // everything is read from what is held right now, so releasing one key can't cancel
+    // another one that is still down
+    fn read(
+        &self,
+        state: &mut PlayerControllerState,
+        keyboard: bool,
+        gamepads: &[Gamepad],
+        action_map: &ActionMap,
+    ) {
+        let processing = &action_map.processing;
+        let key_held = |action: Action| {
+            keyboard
+                && self
+                    .keys
+                    .any_pressed(action_map.keys(action).iter().copied())
+        };
+        let button_value = |action: Action| {
+            gamepads
+                .iter()
+                .flat_map(|gamepad| {
+                    action_map.buttons(action).iter().filter_map(|button| {
+                        self.buttons.get(GamepadButton::new(*gamepad, *button))
+                    })
+                })
+                .fold(0., f32::max)
+        };
+        for action in Action::ALL {
+            // analog buttons like the triggers count as held past halfway
+            let held = key_held(action) || button_value(action) > GAMEPAD_BUTTON_HELD;
+            state.set_held(action, held);
+        }
+        let boost = if key_held(Action::Boost) {
+            1.
+        } else {
+            processing.trigger(button_value(Action::Boost))
+        };
+        // the stick pushed furthest wins, the first gamepad on a tie
+        let stick = gamepads
+            .iter()
+            .map(|gamepad| {
+                let axis = |axis_type| {
+                    self.axes
+                        .get(GamepadAxis::new(*gamepad, axis_type))
+                        .unwrap_or(0.)
+                };
+                Vec2::new(
+                    axis(GamepadAxisType::LeftStickX),
+                    axis(GamepadAxisType::LeftStickY),
+                )
+            })
+            .fold(Vec2::ZERO, |best, stick| {
+                if stick.length_squared() > best.length_squared() {
+                    stick
+                } else {
+                    best
+                }
+            });
+        state.resolve(processing.stick(stick), boost, processing);
+    }
+}
+
 pub struct ControllersPlugin;
 impl Plugin for ControllersPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup)
-            .add_systems(Update, player_controller);
+        let action_map = ActionMap::load().unwrap_or_else(|err| {
+            error!("could not load key bindings: {}", err);
+            ActionMap::default()
+        });
+        app.insert_resource(action_map)
+            .add_systems(Startup, setup)
+            // runs ahead of Update so every gameplay system sees the same state for a frame
+            .add_systems(
+                PreUpdate,
+                player_controller.after(InputSystem).run_if(live_input),
+            )
+            .add_systems(
+                PreUpdate,
+                mouse_follow_controller
+                    .after(player_controller)
+                    .run_if(live_input),
+            );
     }
 }
 
+// alone the player gets every device; in co-op the keyboard stays with player one and
+// each player gets a gamepad of their own, player one only when there are two
 pub fn player_controller(
-    mut key_evr: EventReader<KeyboardInput>,
-    mut joy_evr: EventReader<GamepadAxisChangedEvent>,
-    mut joy_b_evr: EventReader<GamepadButtonChangedEvent>,
-    mut state: ResMut<PlayerControllerState>,
+    devices: InputDevices,
+    setup: Res<PlayerSetup>,
+    mut controllers: ResMut<PlayerControllers>,
+    action_map: Res<ActionMap>,
 ) {
-    use bevy::input::ButtonState;
-    for ev in joy_evr.read() {
-        state.move_player_joystick(ev.value, ev.axis_type)
-    }
-    for ev in joy_b_evr.read() {
-        state.move_player_joystick_buttons(ev.value, ev.button_type)
-    }
-    for ev in key_evr.read() {
-        match ev.state {
-            ButtonState::Pressed => state.move_player(ev.key_code.unwrap_or(KeyCode::Space)),
-            ButtonState::Released => {
-                state.release_move_player(ev.key_code.unwrap_or(KeyCode::Space))
-            }
-        }
+    let gamepads = devices.gamepads();
+    let sources: Vec<(bool, &[Gamepad])> = match (setup.count(), gamepads.as_slice()) {
+        (1, all) => vec![(true, all)],
+        (_, [first, second, ..]) => vec![
+            (true, std::slice::from_ref(first)),
+            (false, std::slice::from_ref(second)),
+        ],
+        (_, all) => vec![(true, &[]), (false, all)],
+    };
+    for (player, (keyboard, gamepads)) in sources.into_iter().enumerate() {
+        devices.read(controllers.get_mut(player), keyboard, gamepads, &action_map);
     }
 }
 
+// steers player one towards the cursor, held keys still win on their axis
+pub fn mouse_follow_controller(
+    mut controllers: ResMut<PlayerControllers>,
+    action_map: Res<ActionMap>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    spider_query: Query<(&Transform, &Spider), SpiderFilter>,
+) {
+    if action_map.scheme != ControlScheme::MouseFollow {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) =
+        (window_query.get_single(), camera_query.get_single())
+    else {
+        return;
+    };
+    // the camera's fixed scaling maps the window onto the 1280x720 playfield
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+    let Some((spider, _)) = spider_query.iter().find(|(_, spider)| spider.player == 0) else {
+        return;
+    };
+    let steer =
+        ((cursor - spider.translation.truncate()) / MOUSE_FOLLOW_SLOWDOWN).clamp_length_max(1.);
+    let state = controllers.get_mut(0);
+    let boost = if mouse_buttons.pressed(MouseButton::Left) {
+        1.
+    } else {
+        state.boost()
+    };
+    state.resolve(steer, boost, &action_map.processing);
+}
+
 pub fn setup(mut commands: Commands) {
-    commands.insert_resource(PlayerControllerState {
-        x: 0.0,
-        y: 0.0,
-        boost: false,
-    });
+    commands.insert_resource(PlayerControllers::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebinding_keeps_menu_keys() {
+        let mut map = ActionMap::default();
+        assert!(map.bind_key(Action::Boost, KeyCode::Space));
+        assert!(map.bind_key(Action::MoveDown, KeyCode::Return));
+        assert_eq!(map.keys(Action::Confirm), [KeyCode::Space, KeyCode::Return]);
+        assert_eq!(map.keys(Action::Boost), [KeyCode::Space]);
+        assert_eq!(map.keys(Action::MoveDown), [KeyCode::Return]);
+    }
+
+    #[test]
+    fn rebinding_moves_keys_between_in_game_actions() {
+        let mut map = ActionMap::default();
+        assert!(map.bind_key(Action::MoveUp, KeyCode::Down));
+        assert_eq!(map.keys(Action::MoveUp), [KeyCode::Down]);
+        assert_eq!(map.keys(Action::MoveDown), [KeyCode::S]);
+        // move down's last key goes, so it takes move left's old keys
+        assert!(map.bind_key(Action::MoveLeft, KeyCode::S));
+        assert_eq!(map.keys(Action::MoveLeft), [KeyCode::S]);
+        assert_eq!(map.keys(Action::MoveDown), [KeyCode::Left, KeyCode::A]);
+    }
+
+    #[test]
+    fn rebinding_never_leaves_an_action_unbound() {
+        let mut map = ActionMap::default();
+        map.bindings
+            .iter_mut()
+            .find(|binding| binding.action == Action::Boost)
+            .unwrap()
+            .buttons
+            .clear();
+        // boost has nothing to swap to move down
+        assert!(!map.bind_button(Action::Boost, GamepadButtonType::DPadDown));
+        assert!(map.buttons(Action::Boost).is_empty());
+        assert_eq!(map.buttons(Action::MoveDown), [GamepadButtonType::DPadDown]);
+    }
+
+    #[test]
+    fn latest_held_direction_wins() {
+        let processing = InputProcessing::default();
+        let mut state = PlayerControllerState::default();
+        state.set_held(Action::MoveLeft, true);
+        state.set_held(Action::MoveRight, true);
+        state.resolve(Vec2::ZERO, 0., &processing);
+        assert_eq!(state.get_state(), (1., 0.));
+        // left is still held, so letting go of right goes back to it
+        state.set_held(Action::MoveRight, false);
+        state.resolve(Vec2::ZERO, 0., &processing);
+        assert_eq!(state.get_state(), (-1., 0.));
+        state.set_held(Action::MoveLeft, false);
+        state.resolve(Vec2::ZERO, 0., &processing);
+        assert!(!state.is_moving());
+    }
+
+    #[test]
+    fn held_directions_override_the_stick_per_axis() {
+        let processing = InputProcessing {
+            normalize_diagonals: false,
+            ..default()
+        };
+        let mut state = PlayerControllerState::default();
+        state.set_held(Action::MoveUp, true);
+        state.resolve(Vec2::new(-0.5, -0.5), 0., &processing);
+        assert_eq!(state.get_state(), (-0.5, 1.));
+    }
+
+    #[test]
+    fn stick_ignores_drift_and_reaches_full_travel() {
+        let processing = InputProcessing::default();
+        assert_eq!(processing.stick(Vec2::new(0.1, 0.1)), Vec2::ZERO);
+        assert_eq!(processing.stick(Vec2::new(0., -0.15)), Vec2::ZERO);
+        // just past the inner edge starts from nothing, the outer edge and past it is full
+        assert!(processing.stick(Vec2::new(0.16, 0.)).x < 0.01);
+        assert_eq!(processing.stick(Vec2::new(0., 0.95)), Vec2::Y);
+        assert_eq!(processing.stick(Vec2::new(-1., 0.)), Vec2::NEG_X);
+        // the direction survives the deadzones
+        let diagonal = processing.stick(Vec2::new(0.5, 0.5));
+        assert!((diagonal.x - diagonal.y).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn stick_follows_the_response_curve() {
+        let linear = InputProcessing {
+            deadzone: 0.,
+            outer_deadzone: 1.,
+            response_curve: 1.,
+            ..default()
+        };
+        let squared = InputProcessing {
+            response_curve: 2.,
+            ..linear.clone()
+        };
+        assert!((linear.stick(Vec2::new(0.5, 0.)).x - 0.5).abs() < 1e-6);
+        assert!((squared.stick(Vec2::new(0.5, 0.)).x - 0.25).abs() < 1e-6);
+        assert_eq!(squared.stick(Vec2::X), Vec2::X);
+    }
+
+    #[test]
+    fn diagonals_are_as_fast_as_straight_lines() {
+        let processing = InputProcessing::default();
+        let diagonal = processing.movement(Vec2::new(1., 1.));
+        assert!((diagonal.length() - 1.).abs() < 1e-6);
+        // slower than full speed is left alone
+        assert_eq!(
+            processing.movement(Vec2::new(0.5, 0.5)),
+            Vec2::new(0.5, 0.5)
+        );
+        let raw = InputProcessing {
+            normalize_diagonals: false,
+            ..default()
+        };
+        assert_eq!(raw.movement(Vec2::new(1., 1.)), Vec2::new(1., 1.));
+    }
+
+    #[test]
+    fn trigger_boosts_in_proportion() {
+        let processing = InputProcessing::default();
+        assert_eq!(processing.trigger(0.05), 0.);
+        assert_eq!(processing.trigger(1.), 1.);
+        let half = processing.trigger(0.525);
+        assert!((half - 0.5).abs() < 1e-6);
+        assert!(processing.trigger(0.3) < half && half < processing.trigger(0.8));
+    }
 }
@@ -0,0 +1,456 @@
+use crate::{
+    bosses::Boss,
+    collision::Collider,
+    entities::{EnemyEntity, EnemyType, PowerUpEntity, PowerUpKind},
+};
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    reflect::TypePath,
+    utils::{BoxedFuture, HashMap},
+};
+use serde::Deserialize;
+use std::{error::Error, fs, marker::PhantomData};
+// data files under assets/data, bundled into the binary and replaced by the asset
+// server's copy once it is loaded
+pub trait Definitions: Asset + Resource + Clone + for<'de> Deserialize<'de> {
+    const PATH: &'static str;
+    const EXTENSIONS: &'static [&'static str];
+    const BUNDLED: &'static str;
+    fn bundled() -> Self {
+        ron::from_str(Self::BUNDLED)
+            .unwrap_or_else(|err| panic!("bundled {} is invalid: {}", Self::PATH, err))
+    }
+    // entries in the order entities index them
+    fn names(&self) -> Vec<&str> {
+        Vec::new()
+    }
+}
+// components holding an index into some definitions, moved to the entry with the same name
+// when the file is reloaded
+pub trait DefinitionIndex: Component {
+    type Definitions: Definitions;
+    fn definition_mut(&mut self) -> &mut usize;
+}
+impl DefinitionIndex for EnemyEntity {
+    type Definitions = EnemyDefinitions;
+    fn definition_mut(&mut self) -> &mut usize {
+        &mut self.definition
+    }
+}
+impl DefinitionIndex for PowerUpEntity {
+    type Definitions = PowerUpDefinitions;
+    fn definition_mut(&mut self) -> &mut usize {
+        &mut self.definition
+    }
+}
+impl DefinitionIndex for Boss {
+    type Definitions = BossDefinitions;
+    fn definition_mut(&mut self) -> &mut usize {
+        &mut self.definition
+    }
+}
+// old index to new index, None where the entry is gone
+#[derive(Event)]
+pub struct DefinitionsReloaded<T: Definitions> {
+    remap: Vec<Option<usize>>,
+    marker: PhantomData<T>,
+}
+#[derive(Deserialize, Clone, Debug)]
+pub struct EnemyDefinition {
+    pub name: String,
+    pub enemy_type: EnemyType,
+    pub sprite: String,
+    pub size: (f32, f32),
+    pub frames: usize,
+    pub animation_speed: f32,
+    pub collider: Collider,
+    pub spawn_weight: f32,
+    pub speed: f32,
+    pub wobble_amplitude: f32,
+    pub wobble_frequency: f32,
+    pub breaks_web: bool,
+    pub score: u32,
+    pub energy: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub tongue: Option<TongueDefinition>,
+    #[serde(default)]
+    pub escaped: EscapeDefinition,
+    // pulled towards the spider while a magnet is running
+    #[serde(default)]
+    pub magnetic: bool,
+}
+// applied once when the bug leaves the playfield without being caught
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct EscapeDefinition {
+    #[serde(default)]
+    pub energy: f32,
+    #[serde(default)]
+    pub score: u32,
+    #[serde(default)]
+    pub breaks_combo: bool,
+}
+// frogs lash out after `interval` seconds, aiming for `telegraph` seconds first
+#[derive(Deserialize, Clone, Debug)]
+pub struct TongueDefinition {
+    pub interval: f32,
+    pub telegraph: f32,
+    pub reach: f32,
+    pub speed: f32,
+    pub hold: f32,
+}
+impl EnemyDefinition {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.size.0, self.size.1)
+    }
+    pub fn energy_gain(&self, energy: f32) -> f32 {
+        self.energy
+            .iter()
+            .find(|(below, _)| energy < *below)
+            .map(|(_, gain)| *gain)
+            .unwrap_or(0.)
+    }
+}
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct EnemyDefinitions {
+    pub enemies: Vec<EnemyDefinition>,
+}
+// weighted pick, roll is expected in 0..1
+fn pick<T>(items: &[T], roll: f32, weight: impl Fn(&T) -> f32) -> Option<usize> {
+    let total: f32 = items.iter().map(&weight).sum();
+    let mut roll = roll * total;
+    for (index, item) in items.iter().enumerate() {
+        if roll < weight(item) {
+            return Some(index);
+        }
+        roll -= weight(item);
+    }
+    None
+}
+impl EnemyDefinitions {
+    pub fn pick(&self, roll: f32, weight: impl Fn(&EnemyDefinition) -> f32) -> Option<usize> {
+        pick(&self.enemies, roll, weight)
+    }
+}
+impl Definitions for EnemyDefinitions {
+    const PATH: &'static str = "data/bugs.enemies.ron";
+    const EXTENSIONS: &'static [&'static str] = &["enemies.ron"];
+    const BUNDLED: &'static str = include_str!("../../assets/data/bugs.enemies.ron");
+    fn names(&self) -> Vec<&str> {
+        self.enemies
+            .iter()
+            .map(|enemy| enemy.name.as_str())
+            .collect()
+    }
+}
+#[derive(Deserialize, Clone, Debug)]
+pub struct PowerUpDefinition {
+    pub name: String,
+    pub kind: PowerUpKind,
+    pub sprite: String,
+    pub size: (f32, f32),
+    pub frames: usize,
+    pub animation_speed: f32,
+    pub collider: Collider,
+    pub spawn_weight: f32,
+    pub speed: f32,
+    // seconds the effect lasts once picked up
+    pub duration: f32,
+}
+impl PowerUpDefinition {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.size.0, self.size.1)
+    }
+}
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct PowerUpDefinitions {
+    pub spawn_interval: f32,
+    pub power_ups: Vec<PowerUpDefinition>,
+}
+impl PowerUpDefinitions {
+    pub fn pick(&self, roll: f32) -> Option<usize> {
+        pick(&self.power_ups, roll, |power_up| power_up.spawn_weight)
+    }
+}
+impl Definitions for PowerUpDefinitions {
+    const PATH: &'static str = "data/pickups.powerups.ron";
+    const EXTENSIONS: &'static [&'static str] = &["powerups.ron"];
+    const BUNDLED: &'static str = include_str!("../../assets/data/pickups.powerups.ron");
+    fn names(&self) -> Vec<&str> {
+        self.power_ups
+            .iter()
+            .map(|power_up| power_up.name.as_str())
+            .collect()
+    }
+}
+// bosses show up one after the other, each once the score reaches its threshold
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossDefinition {
+    pub name: String,
+    pub score: u32,
+    pub sprite: String,
+    pub size: (f32, f32),
+    pub frames: usize,
+    pub animation_speed: f32,
+    pub collider: Collider,
+    // catches it takes to bring the boss down
+    pub health: u32,
+    pub song: String,
+    pub reward_score: u32,
+    pub reward_energy: f32,
+    pub phases: Vec<BossPhase>,
+}
+// a phase runs from its health down to the next phase's health
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossPhase {
+    pub health: u32,
+    pub speed: f32,
+    #[serde(default)]
+    pub dive: Option<DiveAttack>,
+    #[serde(default)]
+    pub summon: Option<SummonAttack>,
+}
+// swoops at a point on the thread every `interval` seconds
+#[derive(Deserialize, Clone, Debug)]
+pub struct DiveAttack {
+    pub interval: f32,
+    pub speed: f32,
+}
+// `minion` is the name of an enemy definition
+#[derive(Deserialize, Clone, Debug)]
+pub struct SummonAttack {
+    pub interval: f32,
+    pub minion: String,
+    pub count: u32,
+}
+impl BossDefinition {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.size.0, self.size.1)
+    }
+    // the phase the boss is in at this health, the first one above every threshold
+    pub fn phase(&self, health: u32) -> Option<&BossPhase> {
+        self.phases
+            .iter()
+            .rev()
+            .find(|phase| health <= phase.health)
+            .or_else(|| self.phases.first())
+    }
+}
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct BossDefinitions {
+    pub bosses: Vec<BossDefinition>,
+}
+impl Definitions for BossDefinitions {
+    const PATH: &'static str = "data/bosses.bosses.ron";
+    const EXTENSIONS: &'static [&'static str] = &["bosses.ron"];
+    const BUNDLED: &'static str = include_str!("../../assets/data/bosses.bosses.ron");
+    fn names(&self) -> Vec<&str> {
+        self.bosses.iter().map(|boss| boss.name.as_str()).collect()
+    }
+}
+// difficulty keyed off the run time, keyframes are interpolated and waves take over
+// spawning while they last
+#[derive(Deserialize, Clone, Debug)]
+pub struct DifficultyKeyframe {
+    pub time: f32,
+    pub spawn_interval: f32,
+    pub speed: f32,
+    // spawn weight multipliers by enemy name, missing names keep their weight
+    #[serde(default)]
+    pub weights: HashMap<String, f32>,
+}
+#[derive(Deserialize, Clone, Debug)]
+pub struct WaveDefinition {
+    pub name: String,
+    pub start: f32,
+    pub duration: f32,
+    pub spawn_interval: f32,
+    // only the named enemies spawn during a wave
+    pub weights: HashMap<String, f32>,
+    // quiet time after the wave where nothing spawns
+    pub breather: f32,
+    #[serde(default)]
+    pub repeat: Option<f32>,
+}
+pub enum WavePhase<'a> {
+    Wave(&'a WaveDefinition),
+    Breather,
+}
+impl WaveDefinition {
+    pub fn phase_at(&self, time: f32) -> Option<WavePhase<'_>> {
+        if time < self.start {
+            return None;
+        }
+        let elapsed = match self.repeat {
+            Some(repeat) if repeat > 0. => (time - self.start) % repeat,
+            _ => time - self.start,
+        };
+        if elapsed < self.duration {
+            Some(WavePhase::Wave(self))
+        } else if elapsed < self.duration + self.breather {
+            Some(WavePhase::Breather)
+        } else {
+            None
+        }
+    }
+}
+#[derive(Asset, Resource, TypePath, Deserialize, Clone, Debug)]
+pub struct DifficultyCurve {
+    pub keyframes: Vec<DifficultyKeyframe>,
+    #[serde(default)]
+    pub waves: Vec<WaveDefinition>,
+}
+impl DifficultyCurve {
+    pub fn wave_at(&self, time: f32) -> Option<WavePhase<'_>> {
+        self.waves.iter().find_map(|wave| wave.phase_at(time))
+    }
+    // linear interpolation between the keyframes around `time`, clamped at both ends
+    pub fn sample(&self, time: f32) -> Option<DifficultyKeyframe> {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        let (from, to) = match next {
+            Some(0) => (self.keyframes.first()?, self.keyframes.first()?),
+            Some(index) => (&self.keyframes[index - 1], &self.keyframes[index]),
+            None => (self.keyframes.last()?, self.keyframes.last()?),
+        };
+        let t = if to.time > from.time {
+            (time - from.time) / (to.time - from.time)
+        } else {
+            0.
+        };
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let mut weights = HashMap::new();
+        for name in from.weights.keys().chain(to.weights.keys()) {
+            let a = from.weights.get(name).copied().unwrap_or(1.);
+            let b = to.weights.get(name).copied().unwrap_or(1.);
+            weights.insert(name.clone(), lerp(a, b));
+        }
+        Some(DifficultyKeyframe {
+            time,
+            spawn_interval: lerp(from.spawn_interval, to.spawn_interval),
+            speed: lerp(from.speed, to.speed),
+            weights,
+        })
+    }
+}
+impl Definitions for DifficultyCurve {
+    const PATH: &'static str = "data/difficulty.waves.ron";
+    const EXTENSIONS: &'static [&'static str] = &["waves.ron"];
+    const BUNDLED: &'static str = include_str!("../../assets/data/difficulty.waves.ron");
+}
+pub struct DefinitionsLoader<T>(PhantomData<T>);
+impl<T: Definitions> AssetLoader for DefinitionsLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = Box<dyn Error + Send + Sync>;
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct RefreshDefinitions;
+#[derive(Resource)]
+struct DefinitionsHandle<T: Definitions>(Handle<T>);
+pub struct DefinitionsPlugin;
+
+impl Plugin for DefinitionsPlugin {
+    fn build(&self, app: &mut App) {
+        add_definitions::<EnemyDefinitions>(app);
+        add_definitions::<DifficultyCurve>(app);
+        add_definitions::<PowerUpDefinitions>(app);
+        add_definitions::<BossDefinitions>(app);
+        app.add_systems(
+            PreUpdate,
+            (
+                remap_definitions::<EnemyEntity>,
+                remap_definitions::<PowerUpEntity>,
+                remap_definitions::<Boss>,
+            )
+                .after(RefreshDefinitions),
+        );
+    }
+}
+
+fn add_definitions<T: Definitions>(app: &mut App) {
+    app.add_event::<DefinitionsReloaded<T>>();
+    if app.is_plugin_added::<AssetPlugin>() {
+        app.insert_resource(T::bundled())
+            .init_asset::<T>()
+            .register_asset_loader(DefinitionsLoader::<T>(PhantomData))
+            .add_systems(Startup, load_definitions::<T>)
+            .add_systems(
+                PreUpdate,
+                refresh_definitions::<T>.in_set(RefreshDefinitions),
+            );
+    } else {
+        // headless runs have no asset server, read the file straight from disk
+        let definitions = fs::read_to_string(format!("assets/{}", T::PATH))
+            .ok()
+            .and_then(|file| ron::from_str::<T>(&file).ok())
+            .unwrap_or_else(T::bundled);
+        app.insert_resource(definitions);
+    }
+}
+
+fn load_definitions<T: Definitions>(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DefinitionsHandle(asset_server.load::<T>(T::PATH)));
+}
+
+// also picks up edits while the game runs when built with the dev feature
+fn refresh_definitions<T: Definitions>(
+    mut events: EventReader<AssetEvent<T>>,
+    mut reloaded: EventWriter<DefinitionsReloaded<T>>,
+    handle: Res<DefinitionsHandle<T>>,
+    assets: Res<Assets<T>>,
+    mut definitions: ResMut<T>,
+) {
+    for event in events.read() {
+        match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } if *id == handle.0.id() => {
+                if let Some(loaded) = assets.get(*id) {
+                    let names = loaded.names();
+                    let remap = definitions
+                        .names()
+                        .iter()
+                        .map(|name| names.iter().position(|loaded| loaded == name))
+                        .collect();
+                    reloaded.send(DefinitionsReloaded {
+                        remap,
+                        marker: PhantomData,
+                    });
+                    *definitions = loaded.clone();
+                    info!("loaded {}", T::PATH);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// live entities keep pointing at the entry they were spawned from, or go with it
+fn remap_definitions<C: DefinitionIndex>(
+    mut commands: Commands,
+    mut events: EventReader<DefinitionsReloaded<C::Definitions>>,
+    mut query: Query<(Entity, &mut C)>,
+) {
+    for event in events.read() {
+        for (entity, mut indexed) in &mut query {
+            let definition = indexed.definition_mut();
+            match event.remap.get(*definition).copied().flatten() {
+                Some(index) => *definition = index,
+                None => commands.entity(entity).despawn(),
+            }
+        }
+    }
+}
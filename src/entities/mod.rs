@@ -1,35 +1,265 @@
 use crate::{
-    controllers::PlayerControllerState,
-    game::{GameState, MOVE_SPEED, SPAWN_TIMER, SPRINGINT_SPEED},
+    collision::Collider,
+    controllers::PlayerControllers,
+    definitions::{EnemyDefinitions, PowerUpDefinitions},
+    game::{
+        Difficulty, GameRng, GameState, PlayerSetup, PowerUpEffects, HASTE_MULTIPLIER, MAGNET_PULL,
+        MAGNET_RANGE, MAX_PLAYERS, MOVE_SPEED, SPAWN_TIMER, SPRINGINT_SPEED,
+    },
 };
-use bevy::{prelude::*, render::camera::ScalingMode};
+use bevy::{asset::AssetPath, ecs::system::SystemParam, prelude::*, render::camera::ScalingMode};
 use rand::Rng;
+use serde::Deserialize;
 use std::time::Duration;
 #[derive(Component)]
 pub struct GameEntity;
 #[derive(Component)]
 pub struct PlayerEntity;
+// the thread a spider hangs from, pointing at that spider
 #[derive(Component)]
-pub struct PlayerAttached;
+pub struct PlayerAttached {
+    pub spider: Entity,
+}
+// which player steers this spider, 0 for player one
+#[derive(Component)]
+pub struct Spider {
+    pub player: usize,
+}
+// the spider itself, without the thread it hangs from
+pub type SpiderFilter = (With<PlayerEntity>, Without<PlayerAttached>);
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EnemyType {
     FLY,
     MOSQUITO,
-    // FROG,
+    FROG,
 }
 #[derive(Component)]
 pub struct EnemyEntity {
     revert_direction: bool,
     pub enemy_type: EnemyType,
+    // index into EnemyDefinitions, looked up every frame so edits apply to live bugs
+    pub definition: usize,
+    // where the bug was before its last move, for swept collision tests
+    pub previous: Vec2,
+    timer: f32,
+}
+impl EnemyEntity {
+    pub fn new(
+        definition: usize,
+        enemy_type: EnemyType,
+        position: Vec2,
+        revert_direction: bool,
+    ) -> Self {
+        Self {
+            revert_direction,
+            enemy_type,
+            definition,
+            previous: position,
+            timer: 0.,
+        }
+    }
+}
+pub const PLAYFIELD: Vec2 = Vec2::new(1280., 720.);
+// sent when a bug leaves the playfield alive, right before it is despawned
+#[derive(Event)]
+pub struct EnemyEscaped {
+    pub definition: usize,
+}
+pub const TONGUE_WIDTH: f32 = 6.;
+// the tongue shoots up, at most this far off vertical, in radians
+pub const TONGUE_CONE: f32 = 0.35;
+pub const WEB_WIDTH: f32 = 3.;
+// the thread hangs from the top edge of the playfield
+pub const WEB_ANCHOR_Y: f32 = 360.;
+// how quickly the anchor slides over the spider, per second
+pub const WEB_ANCHOR_FOLLOW: f32 = 1.5;
+pub const WEB_MIN_LENGTH: f32 = 40.;
+pub const WEB_MAX_LENGTH: f32 = 690.;
+pub const WEB_SEGMENTS: usize = 12;
+pub const WEB_GRAVITY: f32 = 900.;
+// sideways push on the spider per unit of movement speed
+pub const SWING_FORCE: f32 = 5.;
+const ROPE_STEP: f32 = 1. / 120.;
+const ROPE_DAMPING: f32 = 0.995;
+const ROPE_ITERATIONS: usize = 8;
+// the spider outweighs a piece of thread, the anchor doesn't move at all
+const SPIDER_INVERSE_MASS: f32 = 0.25;
+pub const SPIDER_SIZE: f32 = 64.;
+// how far left and right of the middle the spiders start in co-op
+pub const COOP_SPAWN_OFFSET: f32 = 200.;
+// player two is tinted so the spiders can be told apart
+pub const PLAYER_COLORS: [Color; MAX_PLAYERS] = [Color::WHITE, Color::rgb(0.6, 0.9, 1.)];
+// verlet rope from the anchor down to the spider, the last point is the spider
+#[derive(Component)]
+pub struct Web {
+    pub points: Vec<Vec2>,
+    previous: Vec<Vec2>,
+    pub length: f32,
+    pub force: Vec2,
+    accumulator: f32,
+}
+impl Web {
+    pub fn new(anchor: Vec2, spider: Vec2) -> Self {
+        let points: Vec<Vec2> = (0..=WEB_SEGMENTS)
+            .map(|i| anchor.lerp(spider, i as f32 / WEB_SEGMENTS as f32))
+            .collect();
+        Self {
+            previous: points.clone(),
+            points,
+            length: anchor.distance(spider),
+            force: Vec2::ZERO,
+            accumulator: 0.,
+        }
+    }
+    pub fn spider(&self) -> Vec2 {
+        self.points.last().copied().unwrap_or_default()
+    }
+    pub fn segments(&self) -> impl Iterator<Item = Collider> + '_ {
+        self.points
+            .windows(2)
+            .map(|pair| Collider::capsule(pair[0], pair[1], WEB_WIDTH / 2.))
+    }
+    fn inverse_mass(&self, index: usize) -> f32 {
+        match index {
+            0 => 0.,
+            i if i == self.points.len() - 1 => SPIDER_INVERSE_MASS,
+            _ => 1.,
+        }
+    }
+    // fixed steps keep the swing the same at any frame rate, and replays exact
+    pub fn simulate(&mut self, delta: f32) {
+        self.accumulator += delta;
+        while self.accumulator >= ROPE_STEP {
+            self.accumulator -= ROPE_STEP;
+            self.step(ROPE_STEP);
+        }
+    }
+    fn step(&mut self, dt: f32) {
+        let last = self.points.len() - 1;
+        for i in 1..=last {
+            let velocity = (self.points[i] - self.previous[i]) * ROPE_DAMPING;
+            let force = if i == last { self.force } else { Vec2::ZERO };
+            self.previous[i] = self.points[i];
+            self.points[i] += velocity + (Vec2::new(0., -WEB_GRAVITY) + force) * dt * dt;
+        }
+        let rest = self.length / WEB_SEGMENTS as f32;
+        for _ in 0..ROPE_ITERATIONS {
+            for i in 0..last {
+                let delta = self.points[i + 1] - self.points[i];
+                let distance = delta.length();
+                let (a, b) = (self.inverse_mass(i), self.inverse_mass(i + 1));
+                // a thread only pulls, it goes slack when pushed together
+                if distance <= rest || a + b == 0. {
+                    continue;
+                }
+                let correction = delta * ((distance - rest) / distance);
+                self.points[i] += correction * (a / (a + b));
+                self.points[i + 1] -= correction * (b / (a + b));
+            }
+            // keep the spider inside the playfield, dropping its speed against the walls
+            let spider = self.points[last];
+            let bounds = (PLAYFIELD - Vec2::splat(SPIDER_SIZE)) / 2.;
+            let clamped = spider.clamp(-bounds, bounds);
+            if clamped.x != spider.x {
+                self.previous[last].x = clamped.x;
+            }
+            if clamped.y != spider.y {
+                self.previous[last].y = clamped.y;
+            }
+            self.points[last] = clamped;
+        }
+    }
+}
+#[derive(Component)]
+pub struct WebSegment {
+    web: Entity,
+    index: usize,
+}
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+pub enum TonguePhase {
+    #[default]
+    Idle,
+    Telegraph,
+    Lash,
+    Hold,
+    Retract,
+}
+#[derive(Component, Default)]
+pub struct Frog {
+    pub phase: TonguePhase,
+    timer: f32,
+    aim: Vec2,
+    length: f32,
+}
+impl Frog {
+    // the frog stands still from the moment it takes aim until the tongue is back
+    pub fn is_busy(&self) -> bool {
+        self.phase != TonguePhase::Idle
+    }
+    pub fn mouth(frog_pos: Vec3) -> Vec2 {
+        frog_pos.truncate() + Vec2::new(0., 8.)
+    }
+    // only an extended tongue can hurt, the telegraph is just a warning
+    pub fn tongue_collider(&self, frog_pos: Vec3) -> Option<Collider> {
+        match self.phase {
+            TonguePhase::Lash | TonguePhase::Hold => {
+                let mouth = Frog::mouth(frog_pos);
+                Some(Collider::capsule(
+                    mouth,
+                    mouth + self.aim * self.length,
+                    TONGUE_WIDTH / 2.,
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+#[derive(Component)]
+pub struct FrogTongue {
+    frog: Entity,
+}
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerUpKind {
+    Shield,
+    Magnet,
+    DoublePoints,
+    Haste,
+}
+impl PowerUpKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PowerUpKind::Shield => "shield",
+            PowerUpKind::Magnet => "magnet",
+            PowerUpKind::DoublePoints => "x2 points",
+            PowerUpKind::Haste => "haste",
+        }
+    }
+}
+#[derive(Component)]
+pub struct PowerUpEntity {
+    revert_direction: bool,
+    // index into PowerUpDefinitions
+    pub definition: usize,
     timer: f32,
 }
 #[derive(Component)]
 pub struct Score;
 #[derive(Component)]
+pub struct Seed;
+#[derive(Component)]
+pub struct PowerUpHud;
+#[derive(Component)]
+pub struct ModeHud;
+#[derive(Component)]
 pub struct Background;
 #[derive(Resource)]
 struct EnemySpawner {
     timer: Timer,
 }
+#[derive(Resource)]
+struct PowerUpSpawner {
+    timer: Timer,
+}
 #[derive(Component)]
 pub struct AnimationIndices {
     first: usize,
@@ -37,63 +267,149 @@ pub struct AnimationIndices {
 }
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(Timer);
+// loops over every frame of a single row sheet
+pub fn animation(frames: usize, speed: f32) -> (AnimationIndices, AnimationTimer) {
+    (
+        AnimationIndices {
+            first: 0,
+            last: frames.max(1) - 1,
+        },
+        AnimationTimer(Timer::from_seconds(speed, TimerMode::Repeating)),
+    )
+}
 pub struct EntitiesPlugin;
 
+// the asset server and texture atlases are only around when the app is built
+// on top of DefaultPlugins, headless runs get default handles instead
+#[derive(SystemParam)]
+pub struct SpriteAssets<'w> {
+    asset_server: Option<Res<'w, AssetServer>>,
+    texture_atlasses: Option<ResMut<'w, Assets<TextureAtlas>>>,
+}
+impl SpriteAssets<'_> {
+    pub fn load<A: Asset>(&self, path: impl Into<AssetPath<'static>>) -> Handle<A> {
+        match &self.asset_server {
+            Some(asset_server) => asset_server.load(path),
+            None => Handle::default(),
+        }
+    }
+    pub fn atlas(
+        &mut self,
+        path: impl Into<AssetPath<'static>>,
+        tile_size: Vec2,
+        columns: usize,
+        padding: Option<Vec2>,
+    ) -> Handle<TextureAtlas> {
+        match (&self.asset_server, &mut self.texture_atlasses) {
+            (Some(asset_server), Some(texture_atlasses)) => {
+                texture_atlasses.add(TextureAtlas::from_grid(
+                    asset_server.load(path),
+                    tile_size,
+                    columns,
+                    1,
+                    padding,
+                    None,
+                ))
+            }
+            _ => Handle::default(),
+        }
+    }
+}
+
 impl Plugin for EntitiesPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup)
             .add_systems(Update, spawn_entities_on_init)
             .add_systems(Update, move_player)
-            .add_systems(Update, move_web)
+            .add_systems(Update, move_web.after(move_player))
+            .add_systems(Update, render_web.after(move_web))
             .add_systems(Update, animate_sprite)
             .add_systems(Update, spawn_enemies)
+            .add_event::<EnemyEscaped>()
             .add_systems(Update, move_enemies)
-            .add_systems(Update, despawn_game_entities_on_game_over);
+            .add_systems(Update, cull_escaped_enemies.after(move_enemies))
+            .add_systems(Update, spawn_power_ups)
+            .add_systems(Update, move_power_ups)
+            .add_systems(Update, lash_frog_tongues)
+            .add_systems(Update, render_frog_tongues)
+            .add_systems(Update, despawn_orphans)
+            // the run stays on screen behind the game over screen until it is left
+            .add_systems(
+                OnExit(GameState::GameOver),
+                despawn_game_entities_on_game_over,
+            );
     }
 }
 
 pub fn move_web(
-    mut query: Query<&mut Transform, With<PlayerAttached>>,
-    player_query: Query<&mut Transform, (With<PlayerEntity>, Without<PlayerAttached>)>,
+    mut query: Query<(&mut Web, &PlayerAttached)>,
+    mut player_query: Query<&mut Transform, SpiderFilter>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
 ) {
-    for player in &mut player_query.iter() {
-        for mut web in &mut query {
-            web.translation.x = player.translation.x;
-            web.translation.y = player.translation.y + 500.;
+    if *game_state.get() == GameState::Active {
+        let follow = (WEB_ANCHOR_FOLLOW * time.delta_seconds()).min(1.);
+        for (mut web, attached) in &mut query {
+            let Ok(mut player) = player_query.get_mut(attached.spider) else {
+                continue;
+            };
+            let spider = web.spider();
+            web.points[0].x += (spider.x - web.points[0].x) * follow;
+            web.simulate(time.delta_seconds());
+            let spider = web.spider();
+            player.translation.x = spider.x;
+            player.translation.y = spider.y;
         }
     }
 }
 
+pub fn render_web(
+    mut segment_query: Query<(&WebSegment, &mut Transform, &mut Sprite)>,
+    web_query: Query<&Web>,
+) {
+    for (segment, mut transform, mut sprite) in &mut segment_query {
+        let Ok(web) = web_query.get(segment.web) else {
+            continue;
+        };
+        let (Some(from), Some(to)) = (
+            web.points.get(segment.index),
+            web.points.get(segment.index + 1),
+        ) else {
+            continue;
+        };
+        let thread = *from - *to;
+        let center = *to + thread / 2.;
+        transform.translation = Vec3::new(center.x, center.y, 1.);
+        transform.rotation =
+            Quat::from_rotation_arc_2d(Vec2::Y, thread.try_normalize().unwrap_or(Vec2::Y));
+        sprite.custom_size = Some(Vec2::new(WEB_WIDTH, thread.length()));
+    }
+}
+
+// input reels the thread in and out and pushes the spider sideways
 pub fn move_player(
     time: Res<Time>,
-    state: Res<PlayerControllerState>,
-    mut query: Query<&mut Transform, (With<PlayerEntity>, Without<PlayerAttached>)>,
+    controllers: Res<PlayerControllers>,
+    mut query: Query<(&mut Web, &PlayerAttached)>,
+    spider_query: Query<&Spider>,
+    effects: Res<PowerUpEffects>,
     game_state: Res<State<GameState>>,
 ) {
     if *game_state.get() == GameState::Active {
-        let st = state.get_state();
-        let speed: f32 = if state.is_boosting() {
-            SPRINGINT_SPEED
-        } else {
-            MOVE_SPEED
-        };
-        for mut player in &mut query {
-            // check for collisions on x axis
-            if player.translation.x < -618. {
-                player.translation.x = -618.;
-            } else if player.translation.x > 618. {
-                player.translation.x = 618.;
-            } else {
-                player.translation.x += st.0 * speed * time.delta_seconds();
-            }
-            // check on the y axis
-            if player.translation.y < -328. {
-                player.translation.y = -328.;
-            } else if player.translation.y > 328. {
-                player.translation.y = 328.;
-            } else {
-                player.translation.y += st.1 * speed * time.delta_seconds();
+        for (mut web, attached) in &mut query {
+            let Ok(spider) = spider_query.get(attached.spider) else {
+                continue;
+            };
+            let state = controllers.get(spider.player);
+            let st = state.get_state();
+            // boost eases from walking to sprinting with the trigger
+            let mut speed = MOVE_SPEED + (SPRINGINT_SPEED - MOVE_SPEED) * state.boost();
+            if effects.is_active(PowerUpKind::Haste) {
+                speed *= HASTE_MULTIPLIER;
             }
+            web.length = (web.length - st.1 * speed * time.delta_seconds())
+                .clamp(WEB_MIN_LENGTH, WEB_MAX_LENGTH);
+            web.force = Vec2::new(st.0 * speed * SWING_FORCE, 0.);
         }
     }
 }
@@ -123,141 +439,472 @@ pub fn animate_sprite(
 
 fn despawn_game_entities_on_game_over(
     mut commands: Commands,
-    game_state: Res<State<GameState>>,
-    mut next_game_state: ResMut<NextState<GameState>>,
     query: Query<Entity, With<GameEntity>>,
 ) {
-    if *game_state.get() == GameState::GameOver {
-        for entity in query.iter() {
-            commands.entity(entity).despawn();
-        }
-        next_game_state.set(GameState::StartMenu);
+    despawn_game_entities(&mut commands, &query);
+}
+
+// everything that belongs to the current run
+pub fn despawn_game_entities(commands: &mut Commands, query: &Query<Entity, With<GameEntity>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_enemies(
     mut commands: Commands,
     time: Res<Time>,
     mut config: ResMut<EnemySpawner>,
-    asset_server: Res<AssetServer>,
+    mut rng: ResMut<GameRng>,
+    definitions: Res<EnemyDefinitions>,
+    difficulty: Res<Difficulty>,
+    mut sprite_assets: SpriteAssets,
     game_state: Res<State<GameState>>,
-    mut texture_atlasses: ResMut<Assets<TextureAtlas>>,
 ) {
-    if *game_state.get() == GameState::Active {
+    if *game_state.get() == GameState::Active && difficulty.spawning {
+        let interval = Duration::from_secs_f32(difficulty.spawn_interval.max(0.05));
+        if config.timer.duration() != interval {
+            config.timer.set_duration(interval);
+        }
         config.timer.tick(time.delta());
         if config.timer.finished() {
-            let mut rng = rand::thread_rng();
-            let enemy_type = match rand::random::<f32>() {
-                x if x < 0.7 => EnemyType::FLY,
-                _ => EnemyType::MOSQUITO,
-            };
-            let size = match enemy_type {
-                EnemyType::FLY => Vec2 { x: 16., y: 16. },
-                EnemyType::MOSQUITO => Vec2 { x: 16., y: 10. },
-            };
-            let sprite = match enemy_type {
-                EnemyType::FLY => "sprites/fly.png",
-                EnemyType::MOSQUITO => "sprites/mosquito.png",
-            };
-            let animation_indices = AnimationIndices { first: 0, last: 1 };
-            let animation_timer = match enemy_type {
-                EnemyType::FLY => AnimationTimer(Timer::from_seconds(0.5, TimerMode::Repeating)),
-                EnemyType::MOSQUITO => {
-                    AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating))
-                }
+            let Some(definition) =
+                definitions.pick(rng.gen::<f32>(), |enemy| difficulty.spawn_weight(enemy))
+            else {
+                return;
             };
+            let enemy = &definitions.enemies[definition];
+            let size = enemy.size();
             let height = 720. - size.y;
             let width = 1280. - size.x;
             let half_height = height / 2.;
             let half_width = width / 2.;
-            let revert_direction = rand::random::<bool>();
-            let y: f32 = (rng.gen::<f32>() * height) - half_height;
+            let revert_direction = rng.gen::<bool>();
+            let y: f32 = match enemy.enemy_type {
+                // frogs sit on the floor of the playfield
+                EnemyType::FROG => {
+                    rng.gen::<f32>();
+                    -(720. - size.y * 2.) / 2.
+                }
+                _ => (rng.gen::<f32>() * height) - half_height,
+            };
             let x: f32 = if revert_direction {
                 half_width - size.x
             } else {
                 (half_width * -1.) + size.x
             };
-
-            let enemy_atlas = TextureAtlas::from_grid(
-                asset_server.load(sprite),
-                size,
-                2,
-                1,
-                Some(Vec2::splat(1.)),
-                None,
+            spawn_enemy(
+                &mut commands,
+                &mut sprite_assets,
+                &definitions,
+                definition,
+                Vec2::new(x, y),
+                revert_direction,
             );
-            let enemy_atlas_handle = texture_atlasses.add(enemy_atlas);
+        }
+    }
+}
+
+// also used for bugs that don't come in from the edges, like a boss's minions
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    sprite_assets: &mut SpriteAssets,
+    definitions: &EnemyDefinitions,
+    definition: usize,
+    position: Vec2,
+    revert_direction: bool,
+) {
+    let Some(enemy) = definitions.enemies.get(definition) else {
+        return;
+    };
+    let size = enemy.size();
+    let (animation_indices, animation_timer) = animation(enemy.frames, enemy.animation_speed);
+    let enemy_atlas_handle = sprite_assets.atlas(
+        enemy.sprite.clone(),
+        size,
+        enemy.frames,
+        Some(Vec2::splat(1.)),
+    );
+    let mut enemy_entity = commands.spawn((
+        GameEntity,
+        enemy.collider.clone(),
+        EnemyEntity::new(definition, enemy.enemy_type, position, revert_direction),
+        SpriteSheetBundle {
+            texture_atlas: enemy_atlas_handle,
+            transform: Transform {
+                translation: position.extend(2.),
+                ..default()
+            },
+            sprite: TextureAtlasSprite {
+                index: animation_indices.first,
+                custom_size: Some(size * 2.),
+                flip_x: revert_direction,
+                ..default()
+            },
+            ..default()
+        },
+        animation_indices,
+        animation_timer,
+    ));
+    if enemy.enemy_type == EnemyType::FROG {
+        enemy_entity.insert(Frog::default());
+        let frog = enemy_entity.id();
+        commands.spawn((
+            GameEntity,
+            FrogTongue { frog },
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(TONGUE_WIDTH, 0.)),
+                    color: Color::rgb(0.91, 0.43, 0.55),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
+}
+
+pub fn move_enemies(
+    mut query: Query<(&mut Transform, &mut EnemyEntity, Option<&Frog>)>,
+    player_query: Query<&Transform, (SpiderFilter, Without<EnemyEntity>)>,
+    definitions: Res<EnemyDefinitions>,
+    difficulty: Res<Difficulty>,
+    effects: Res<PowerUpEffects>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() == GameState::Active {
+        let spiders: Vec<Vec2> = if effects.is_active(PowerUpKind::Magnet) {
+            player_query
+                .iter()
+                .map(|player| player.translation.truncate())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for mut enemy in &mut query {
+            enemy.1.timer += time.delta_seconds();
+            let Some(definition) = definitions.enemies.get(enemy.1.definition) else {
+                continue;
+            };
+            enemy.1.previous = enemy.0.translation.truncate();
+            if enemy.2.is_some_and(|frog| frog.is_busy()) {
+                continue;
+            }
+            let movement: f32 = time.delta_seconds() * definition.speed * difficulty.speed;
+            enemy.0.translation.x += if enemy.1.revert_direction {
+                -movement
+            } else {
+                movement
+            };
+            // vertical oscillation
+            enemy.0.translation.y += movement
+                * definition.wobble_amplitude
+                * (enemy.1.timer * definition.wobble_frequency).sin();
+            // on top of their own movement, without overshooting the spider
+            let nearest = nearest_spider(&spiders, enemy.0.translation.truncate());
+            if let Some(spider) = nearest.filter(|_| definition.magnetic) {
+                let to_spider = spider - enemy.0.translation.truncate();
+                let distance = to_spider.length();
+                if distance > 0. && distance < MAGNET_RANGE {
+                    let pull = (MAGNET_PULL * time.delta_seconds()).min(distance);
+                    enemy.0.translation += (to_spider / distance * pull).extend(0.);
+                }
+            }
+        }
+    }
+}
+
+// bugs that go after a spider pick the closest one
+fn nearest_spider(spiders: &[Vec2], from: Vec2) -> Option<Vec2> {
+    spiders.iter().copied().min_by(|a, b| {
+        a.distance_squared(from)
+            .total_cmp(&b.distance_squared(from))
+    })
+}
+
+fn spawn_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut spawner: ResMut<PowerUpSpawner>,
+    mut rng: ResMut<GameRng>,
+    definitions: Res<PowerUpDefinitions>,
+    mut sprite_assets: SpriteAssets,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() == GameState::Active {
+        let interval = Duration::from_secs_f32(definitions.spawn_interval.max(1.));
+        if spawner.timer.duration() != interval {
+            spawner.timer.set_duration(interval);
+        }
+        spawner.timer.tick(time.delta());
+        if spawner.timer.finished() {
+            let Some(definition) = definitions.pick(rng.gen::<f32>()) else {
+                return;
+            };
+            let power_up = &definitions.power_ups[definition];
+            let size = power_up.size();
+            let revert_direction = rng.gen::<bool>();
+            // keep clear of the floor and the top edge where the thread hangs from, a
+            // power up too big for that still gets a sliver around the middle
+            let half_height = ((PLAYFIELD.y - size.y * 8.) / 2.).max(1.);
+            let y = rng.gen_range(-half_height..half_height);
+            let x = if revert_direction {
+                (PLAYFIELD.x - size.x) / 2.
+            } else {
+                -(PLAYFIELD.x - size.x) / 2.
+            };
+            let animation_indices = AnimationIndices {
+                first: 0,
+                last: power_up.frames.max(1) - 1,
+            };
             commands.spawn((
                 GameEntity,
-                EnemyEntity {
+                power_up.collider.clone(),
+                PowerUpEntity {
                     revert_direction,
-                    enemy_type,
+                    definition,
                     timer: 0.,
                 },
                 SpriteSheetBundle {
-                    texture_atlas: enemy_atlas_handle,
-                    transform: Transform {
-                        translation: Vec3::from((x, y, 2.)),
-                        ..default()
-                    },
+                    texture_atlas: sprite_assets.atlas(
+                        power_up.sprite.clone(),
+                        size,
+                        power_up.frames,
+                        Some(Vec2::splat(1.)),
+                    ),
+                    transform: Transform::from_translation(Vec3::new(x, y, 3.)),
                     sprite: TextureAtlasSprite {
                         index: animation_indices.first,
                         custom_size: Some(size * 2.),
-                        flip_x: revert_direction,
                         ..default()
                     },
                     ..default()
                 },
                 animation_indices,
-                animation_timer,
+                AnimationTimer(Timer::from_seconds(
+                    power_up.animation_speed,
+                    TimerMode::Repeating,
+                )),
             ));
         }
     }
 }
 
-fn move_enemies(
-    mut query: Query<(&mut Transform, &mut EnemyEntity)>,
+// power-ups bob across the playfield and are gone once they reach the other side
+fn move_power_ups(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &mut PowerUpEntity)>,
+    definitions: Res<PowerUpDefinitions>,
     time: Res<Time>,
     game_state: Res<State<GameState>>,
 ) {
     if *game_state.get() == GameState::Active {
-        for mut enemy in &mut query {
-            enemy.1.timer += time.delta_seconds();
-            match enemy.1.enemy_type {
-                EnemyType::FLY => {
-                    let movement: f32 = time.delta_seconds() * 64.;
-                    enemy.0.translation.x += if enemy.1.revert_direction {
-                        movement * -1.
-                    } else {
-                        movement
-                    };
-                    // vertical oscillation
-                    enemy.0.translation.y += movement * (enemy.1.timer * 3.).sin();
+        for (entity, mut transform, mut power_up) in &mut query {
+            let Some(definition) = definitions.power_ups.get(power_up.definition) else {
+                continue;
+            };
+            power_up.timer += time.delta_seconds();
+            let movement = time.delta_seconds() * definition.speed;
+            transform.translation.x += if power_up.revert_direction {
+                -movement
+            } else {
+                movement
+            };
+            transform.translation.y += movement * (power_up.timer * 2.).sin();
+            if transform.translation.x.abs() > PLAYFIELD.x / 2. + definition.size().x {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+pub fn cull_escaped_enemies(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &EnemyEntity)>,
+    definitions: Res<EnemyDefinitions>,
+    mut escaped: EventWriter<EnemyEscaped>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() == GameState::Active {
+        for (entity, transform, enemy) in &query {
+            // sprites are drawn at twice their size, so half the drawn size is the png size
+            let margin = definitions
+                .enemies
+                .get(enemy.definition)
+                .map(|definition| definition.size())
+                .unwrap_or_default();
+            let limit = PLAYFIELD / 2. + margin;
+            let position = transform.translation.truncate();
+            if position.x.abs() > limit.x || position.y.abs() > limit.y {
+                commands.entity(entity).despawn();
+                escaped.send(EnemyEscaped {
+                    definition: enemy.definition,
+                });
+            }
+        }
+    }
+}
+
+// idle -> telegraph (aim locked on the spider) -> lash -> hold -> retract -> idle
+pub fn lash_frog_tongues(
+    mut frog_query: Query<(&Transform, &EnemyEntity, &mut Frog, &mut TextureAtlasSprite)>,
+    player_query: Query<&Transform, SpiderFilter>,
+    definitions: Res<EnemyDefinitions>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() == GameState::Active {
+        let delta = time.delta_seconds();
+        let spiders: Vec<Vec2> = player_query
+            .iter()
+            .map(|player| player.translation.truncate())
+            .collect();
+        for (transform, enemy, mut frog, mut sprite) in &mut frog_query {
+            let Some(tongue) = definitions
+                .enemies
+                .get(enemy.definition)
+                .and_then(|definition| definition.tongue.as_ref())
+            else {
+                continue;
+            };
+            frog.timer += delta;
+            match frog.phase {
+                TonguePhase::Idle => {
+                    if frog.timer >= tongue.interval {
+                        let mouth = Frog::mouth(transform.translation);
+                        if let Some(player) = nearest_spider(&spiders, mouth) {
+                            // locked on where the spider is now, but always upward
+                            let angle = (player - mouth)
+                                .try_normalize()
+                                .map_or(0., |aim| Vec2::Y.angle_between(aim))
+                                .clamp(-TONGUE_CONE, TONGUE_CONE);
+                            frog.aim = Vec2::from_angle(angle).rotate(Vec2::Y);
+                            frog.phase = TonguePhase::Telegraph;
+                            frog.timer = 0.;
+                        }
+                    }
+                }
+                TonguePhase::Telegraph => {
+                    if frog.timer >= tongue.telegraph {
+                        frog.phase = TonguePhase::Lash;
+                        frog.timer = 0.;
+                    }
+                }
+                TonguePhase::Lash => {
+                    frog.length = (frog.length + tongue.speed * delta).min(tongue.reach);
+                    if frog.length >= tongue.reach {
+                        frog.phase = TonguePhase::Hold;
+                        frog.timer = 0.;
+                    }
                 }
-                EnemyType::MOSQUITO => {
-                    let movement: f32 = time.delta_seconds() * 256.;
-                    enemy.0.translation.x += if enemy.1.revert_direction {
-                        movement * -1.
-                    } else {
-                        movement
+                TonguePhase::Hold => {
+                    if frog.timer >= tongue.hold {
+                        frog.phase = TonguePhase::Retract;
+                        frog.timer = 0.;
+                    }
+                }
+                TonguePhase::Retract => {
+                    frog.length = (frog.length - tongue.speed * delta).max(0.);
+                    if frog.length <= 0. {
+                        frog.phase = TonguePhase::Idle;
+                        frog.timer = 0.;
                     }
                 }
             }
+            sprite.color = match frog.phase {
+                TonguePhase::Telegraph => Color::rgb(1., 0.5, 0.5),
+                _ => Color::WHITE,
+            };
+        }
+    }
+}
+
+fn render_frog_tongues(
+    mut tongue_query: Query<(&FrogTongue, &mut Transform, &mut Sprite, &mut Visibility)>,
+    frog_query: Query<(&Transform, &EnemyEntity, &Frog), Without<FrogTongue>>,
+    definitions: Res<EnemyDefinitions>,
+) {
+    for (tongue, mut transform, mut sprite, mut visibility) in &mut tongue_query {
+        let Ok((frog_transform, enemy, frog)) = frog_query.get(tongue.frog) else {
+            continue;
+        };
+        let reach = definitions
+            .enemies
+            .get(enemy.definition)
+            .and_then(|definition| definition.tongue.as_ref())
+            .map_or(0., |tongue| tongue.reach);
+        // the telegraph draws a faint full length line where the tongue will land
+        let (length, alpha) = match frog.phase {
+            TonguePhase::Idle => (0., 0.),
+            TonguePhase::Telegraph => (reach, 0.25),
+            _ => (frog.length, 1.),
+        };
+        *visibility = if length > 0. {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        let mouth = Frog::mouth(frog_transform.translation);
+        let center = mouth + frog.aim * length / 2.;
+        transform.translation = Vec3::new(center.x, center.y, 3.);
+        transform.rotation =
+            Quat::from_rotation_arc_2d(Vec2::Y, frog.aim.try_normalize().unwrap_or(Vec2::Y));
+        sprite.custom_size = Some(Vec2::new(TONGUE_WIDTH, length));
+        sprite.color.set_a(alpha);
+    }
+}
+
+// tongues and thread segments go away with the frog or web they belong to
+fn despawn_orphans(
+    mut commands: Commands,
+    tongue_query: Query<(Entity, &FrogTongue)>,
+    segment_query: Query<(Entity, &WebSegment)>,
+    web_owner_query: Query<(Entity, &PlayerAttached)>,
+    frog_query: Query<(), With<Frog>>,
+    web_query: Query<(), With<Web>>,
+    spider_query: Query<(), With<Spider>>,
+) {
+    for (entity, tongue) in &tongue_query {
+        if frog_query.get(tongue.frog).is_err() {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, segment) in &segment_query {
+        if web_query.get(segment.web).is_err() {
+            commands.entity(entity).despawn();
+        }
+    }
+    for (entity, attached) in &web_owner_query {
+        if spider_query.get(attached.spider).is_err() {
+            commands.entity(entity).despawn();
         }
     }
 }
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn setup(
+    mut commands: Commands,
+    sprite_assets: SpriteAssets,
+    power_ups: Res<PowerUpDefinitions>,
+) {
     commands.insert_resource(EnemySpawner {
         timer: Timer::new(Duration::from_secs_f32(SPAWN_TIMER), TimerMode::Repeating),
     });
+    commands.insert_resource(PowerUpSpawner {
+        timer: Timer::new(
+            Duration::from_secs_f32(power_ups.spawn_interval.max(1.)),
+            TimerMode::Repeating,
+        ),
+    });
     commands.spawn((
         TextBundle::from_sections([TextSection::new(
             "",
             TextStyle {
                 font_size: 64.,
                 color: Color::WHITE,
-                font: asset_server.load("fonts/bigblueterm.ttf"),
+                font: sprite_assets.load("fonts/bigblueterm.ttf"),
             },
         )])
         .with_style(Style {
@@ -268,6 +915,58 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         }),
         Score,
     ));
+    commands.spawn((
+        TextBundle::from_sections([TextSection::new(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                font: sprite_assets.load("fonts/bigblueterm.ttf"),
+            },
+        )])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(15.),
+            left: Val::Px(25.),
+            ..default()
+        }),
+        Seed,
+    ));
+    commands.spawn((
+        TextBundle::from_sections([TextSection::new(
+            "",
+            TextStyle {
+                font_size: 20.,
+                color: Color::WHITE,
+                font: sprite_assets.load("fonts/bigblueterm.ttf"),
+            },
+        )])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(15.),
+            left: Val::Px(25.),
+            ..default()
+        }),
+        PowerUpHud,
+    ));
+    commands.spawn((
+        TextBundle::from_sections([TextSection::new(
+            "",
+            TextStyle {
+                font_size: 32.,
+                color: Color::WHITE,
+                font: sprite_assets.load("fonts/bigblueterm.ttf"),
+            },
+        )])
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(15.),
+            width: Val::Percent(100.),
+            ..default()
+        }),
+        ModeHud,
+    ));
     commands.spawn(Camera2dBundle {
         projection: OrthographicProjection {
             scaling_mode: ScalingMode::Fixed {
@@ -284,16 +983,22 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
 
 pub fn spawn_entities_on_init(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    mut sprite_assets: SpriteAssets,
     game_state: Res<State<GameState>>,
     mut next_game_state: ResMut<NextState<GameState>>,
-    mut texture_atlasses: ResMut<Assets<TextureAtlas>>,
+    mut rng: ResMut<GameRng>,
+    mut effects: ResMut<PowerUpEffects>,
+    setup: Res<PlayerSetup>,
 ) {
     if *game_state.get() == GameState::Init {
+        rng.reseed();
+        effects.clear();
+        info!("starting run with seed {}", rng.seed());
         commands.spawn((
             Background,
+            GameEntity,
             SpriteBundle {
-                texture: asset_server.load("sprites/bgblur.png"),
+                texture: sprite_assets.load("sprites/bgblur.png"),
                 transform: Transform {
                     translation: Vec3::from((0., 0., 0.)),
                     scale: Vec3::from((2., 2., 1.)),
@@ -302,49 +1007,61 @@ pub fn spawn_entities_on_init(
                 ..default()
             },
         ));
-        let spider_atlas = TextureAtlas::from_grid(
-            asset_server.load("sprites/spooder.png"),
-            Vec2 { x: 32., y: 32. },
-            2,
-            1,
-            None,
-            None,
-        );
-        let spider_atlas_handle = texture_atlasses.add(spider_atlas);
-        let animation_indices = AnimationIndices { first: 0, last: 1 };
-        commands.spawn((
-            PlayerEntity,
-            GameEntity,
-            SpriteSheetBundle {
-                texture_atlas: spider_atlas_handle,
-                transform: Transform::from_translation(Vec3::new(0., 0., 10.)),
-                sprite: TextureAtlasSprite {
-                    index: animation_indices.first,
-                    custom_size: Some(Vec2::splat(64.)),
-                    ..default()
-                },
-                ..default()
-            },
-            animation_indices,
-            AnimationTimer(Timer::from_seconds(0.5, TimerMode::Repeating)),
-        ));
-        commands.spawn((
-            PlayerEntity,
-            GameEntity,
-            PlayerAttached,
-            SpriteBundle {
-                sprite: Sprite {
-                    custom_size: Some(Vec2::new(3.0, 1000.0)),
-                    color: Color::WHITE,
-                    ..default()
-                },
-                transform: Transform {
-                    translation: Vec3::from((0., 500., 1.)),
-                    ..default()
-                },
-                ..default()
-            },
-        ));
+        let spider_atlas_handle =
+            sprite_assets.atlas("sprites/spooder.png", Vec2 { x: 32., y: 32. }, 2, None);
+        for (player, color) in PLAYER_COLORS.into_iter().enumerate().take(setup.count()) {
+            // side by side in co-op, player one on the left
+            let x = if setup.count() > 1 {
+                (player as f32 - 0.5) * 2. * COOP_SPAWN_OFFSET
+            } else {
+                0.
+            };
+            let animation_indices = AnimationIndices { first: 0, last: 1 };
+            let spider = commands
+                .spawn((
+                    PlayerEntity,
+                    GameEntity,
+                    Spider { player },
+                    // the body only, the legs and the transparent border don't count
+                    Collider::circle(20., Vec2::new(0., 4.)),
+                    SpriteSheetBundle {
+                        texture_atlas: spider_atlas_handle.clone(),
+                        transform: Transform::from_translation(Vec3::new(x, 0., 10.)),
+                        sprite: TextureAtlasSprite {
+                            index: animation_indices.first,
+                            custom_size: Some(Vec2::splat(SPIDER_SIZE)),
+                            color,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    animation_indices,
+                    AnimationTimer(Timer::from_seconds(0.5, TimerMode::Repeating)),
+                ))
+                .id();
+            let web = commands
+                .spawn((
+                    PlayerEntity,
+                    GameEntity,
+                    PlayerAttached { spider },
+                    Web::new(Vec2::new(x, WEB_ANCHOR_Y), Vec2::new(x, 0.)),
+                ))
+                .id();
+            for index in 0..WEB_SEGMENTS {
+                commands.spawn((
+                    GameEntity,
+                    WebSegment { web, index },
+                    SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(WEB_WIDTH, 0.)),
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ));
+            }
+        }
         next_game_state.set(GameState::Active);
     }
 }
@@ -1,18 +1,48 @@
 use crate::{
-    controllers::PlayerControllerState,
-    entities::{EnemyEntity, EnemyType, PlayerAttached, PlayerEntity, Score},
+    bosses::BossFight,
+    collision::{Collider, SpatialGrid},
+    controllers::{Action, ActionInput, PlayerControllers},
+    definitions::{
+        DifficultyCurve, EnemyDefinition, EnemyDefinitions, PowerUpDefinitions, WavePhase,
+    },
+    entities::{
+        cull_escaped_enemies, lash_frog_tongues, move_enemies, move_web, EnemyEntity, EnemyEscaped,
+        EnemyType, Frog, ModeHud, PlayerAttached, PowerUpEntity, PowerUpHud, PowerUpKind, Score,
+        Seed, Spider, SpiderFilter, Web,
+    },
+    replay::live_input,
 };
 use bevy::{
-    input::gamepad::GamepadButtonChangedEvent,
     prelude::*,
-    sprite::collide_aabb::{collide, Collision},
+    utils::{HashMap, HashSet},
 };
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
 pub const IDLE_ENERGY_BURNING_RATE: f32 = 0.075;
 pub const MOVING_ENERGY_BURNING_RATE: f32 = 0.125;
 pub const SPRINTING_ENERGY_BURNING_RATE: f32 = 0.30;
 pub const SPRINGINT_SPEED: f32 = 256.0;
 pub const MOVE_SPEED: f32 = 128.0;
 pub const SPAWN_TIMER: f32 = 0.8;
+// contacts whose normal points further down than this come from below the spider
+pub const HIT_FROM_BELOW: f32 = -0.7;
+// a bit bigger than the largest bug so most of them only touch a few cells
+pub const GRID_CELL_SIZE: f32 = 64.;
+// seconds between catches before the combo drops
+pub const COMBO_WINDOW: f32 = 1.5;
+// catches in a row per multiplier step, boosted catches count twice
+pub const COMBO_STEP: u32 = 4;
+pub const COMBO_MAX_MULTIPLIER: u32 = 8;
+// magnetic bugs closer than the range drift towards the spider at this speed
+pub const MAGNET_RANGE: f32 = 320.;
+pub const MAGNET_PULL: f32 = 160.;
+pub const HASTE_MULTIPLIER: f32 = 1.5;
+// bosses patrol along this height between attacks
+pub const BOSS_HOVER_Y: f32 = 220.;
+// seconds a boss can't be caught again after a catch
+pub const BOSS_INVULNERABILITY: f32 = 1.2;
+pub const TIME_ATTACK_DURATION: f32 = 90.;
+pub const MAX_PLAYERS: usize = 2;
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum GameState {
     #[default]
@@ -22,11 +52,250 @@ pub enum GameState {
     Pause,
     Init,
 }
+// the ruleset picked on the start menu, it can't change during a run
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum GameMode {
+    // energy burns until it runs out
+    #[default]
+    Endless,
+    // best score in TIME_ATTACK_DURATION seconds, running out of energy still ends it early
+    TimeAttack,
+    // no energy to worry about and bugs that break the thread are harmless, for practice
+    Zen,
+}
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Endless, GameMode::TimeAttack, GameMode::Zen];
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::TimeAttack => "time attack",
+            GameMode::Zen => "zen",
+        }
+    }
+    // used for file names and urls
+    pub fn key(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::TimeAttack => "time-attack",
+            GameMode::Zen => "zen",
+        }
+    }
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.key() == key)
+    }
+    pub fn cycle(&self, step: isize) -> Self {
+        cycle(&Self::ALL, *self, step)
+    }
+    pub fn burns_energy(&self) -> bool {
+        *self != GameMode::Zen
+    }
+}
+// how many spiders a run starts with and whether they eat from the same energy bar
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayerSetup {
+    #[default]
+    Solo,
+    SharedEnergy,
+    SeparateEnergy,
+}
+impl PlayerSetup {
+    pub const ALL: [PlayerSetup; 3] = [
+        PlayerSetup::Solo,
+        PlayerSetup::SharedEnergy,
+        PlayerSetup::SeparateEnergy,
+    ];
+    pub fn label(&self) -> &'static str {
+        match self {
+            PlayerSetup::Solo => "1 player",
+            PlayerSetup::SharedEnergy => "2 players, shared energy",
+            PlayerSetup::SeparateEnergy => "2 players, own energy",
+        }
+    }
+    pub fn count(&self) -> usize {
+        match self {
+            PlayerSetup::Solo => 1,
+            _ => MAX_PLAYERS,
+        }
+    }
+    // the index into GameResources::energy a player burns from and refills
+    pub fn energy_pool(&self, player: usize) -> usize {
+        match self {
+            PlayerSetup::SeparateEnergy => player,
+            _ => 0,
+        }
+    }
+    pub fn energy_pools(&self) -> usize {
+        match self {
+            PlayerSetup::SeparateEnergy => MAX_PLAYERS,
+            _ => 1,
+        }
+    }
+    pub fn cycle(&self, step: isize) -> Self {
+        cycle(&Self::ALL, *self, step)
+    }
+}
+// steps through all, wrapping around at both ends
+fn cycle<T: Copy + PartialEq>(all: &[T], current: T, step: isize) -> T {
+    let index = all.iter().position(|item| *item == current).unwrap_or(0) as isize;
+    all[(index + step).rem_euclid(all.len() as isize) as usize]
+}
+// what ended a spider's run, for the game over screen
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeathCause {
+    // a bug or a diving boss cut the thread, by name
+    SnappedThread(String),
+    HitFromBelow(String),
+    EatenBy(String),
+    Starved,
+}
+impl DeathCause {
+    pub fn describe(&self) -> String {
+        match self {
+            DeathCause::SnappedThread(name) => format!("a {} snapped the thread", name),
+            DeathCause::HitFromBelow(name) => format!("a {} hit from below", name),
+            DeathCause::EatenBy(name) => format!("eaten by a {}", name),
+            DeathCause::Starved => "starved".into(),
+        }
+    }
+}
 #[derive(Resource)]
 pub struct GameResources {
-    pub energy: f32,
+    // one pool per player when they don't share, unused pools stay full
+    pub energy: [f32; MAX_PLAYERS],
     pub score: u32,
     pub time: f32,
+    pub combo: u32,
+    pub multiplier: u32,
+    combo_timer: f32,
+    pub flies: u32,
+    pub mosquitoes: u32,
+    // the latest spider to go, which in co-op is the one that ended the run
+    pub death: Option<DeathCause>,
+}
+impl GameResources {
+    pub fn reset(&mut self) {
+        self.score = 0;
+        self.time = 0.;
+        self.energy = [1.; MAX_PLAYERS];
+        self.flies = 0;
+        self.mosquitoes = 0;
+        self.death = None;
+        self.break_combo();
+    }
+    pub fn catch(&mut self, score: u32, boosting: bool) {
+        self.combo += if boosting { 2 } else { 1 };
+        self.combo_timer = COMBO_WINDOW;
+        self.multiplier = (1 + self.combo / COMBO_STEP).min(COMBO_MAX_MULTIPLIER);
+        self.score += score * self.multiplier;
+    }
+    pub fn break_combo(&mut self) {
+        self.combo = 0;
+        self.multiplier = 1;
+        self.combo_timer = 0.;
+    }
+    // a dead spider ends the combo along with the run
+    pub fn kill(&mut self, cause: DeathCause) {
+        self.death = Some(cause);
+        self.break_combo();
+    }
+}
+// every gameplay roll goes through this rng so a run can be replayed from its seed
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng {
+    seed: u64,
+    fixed: bool,
+    #[deref]
+    rng: StdRng,
+}
+impl GameRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        let fixed = seed.is_some();
+        let seed = seed.unwrap_or_else(rand::random);
+        Self {
+            seed,
+            fixed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    // starts the sequence over, picking a fresh seed unless one was given at startup
+    pub fn reseed(&mut self) {
+        if !self.fixed {
+            self.seed = rand::random();
+        }
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+// seconds left on every running power-up, in the order they were picked up
+#[derive(Resource, Default)]
+pub struct PowerUpEffects {
+    timers: Vec<(PowerUpKind, f32)>,
+}
+impl PowerUpEffects {
+    // picking up a running effect again restarts it
+    pub fn activate(&mut self, kind: PowerUpKind, duration: f32) {
+        match self.timers.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, remaining)) => *remaining = remaining.max(duration),
+            None => self.timers.push((kind, duration)),
+        }
+    }
+    pub fn is_active(&self, kind: PowerUpKind) -> bool {
+        self.timers.iter().any(|(k, _)| *k == kind)
+    }
+    // for effects that run out early, like a shield taking a hit
+    pub fn consume(&mut self, kind: PowerUpKind) {
+        self.timers.retain(|(k, _)| *k != kind);
+    }
+    pub fn clear(&mut self) {
+        self.timers.clear();
+    }
+    fn tick(&mut self, delta: f32) {
+        for (_, remaining) in &mut self.timers {
+            *remaining -= delta;
+        }
+        self.timers.retain(|(_, remaining)| *remaining > 0.);
+    }
+}
+// what the difficulty curve asks for at the current run time
+#[derive(Resource)]
+pub struct Difficulty {
+    pub spawn_interval: f32,
+    pub speed: f32,
+    pub spawning: bool,
+    pub wave: Option<String>,
+    weights: HashMap<String, f32>,
+    default_weight: f32,
+}
+impl Difficulty {
+    pub fn spawn_weight(&self, enemy: &EnemyDefinition) -> f32 {
+        enemy.spawn_weight
+            * self
+                .weights
+                .get(&enemy.name)
+                .copied()
+                .unwrap_or(self.default_weight)
+    }
+}
+impl Default for Difficulty {
+    fn default() -> Self {
+        Self {
+            spawn_interval: SPAWN_TIMER,
+            speed: 1.,
+            spawning: true,
+            wave: None,
+            weights: HashMap::new(),
+            default_weight: 1.,
+        }
+    }
 }
 pub struct GamePlugin;
 
@@ -34,21 +303,51 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, init)
             .add_state::<GameState>()
-            .add_systems(Update, detect_intersection_player)
-            .add_systems(Update, toggle_pause)
-            .add_systems(Update, toggle_start)
+            .init_resource::<GameRng>()
+            .init_resource::<GameMode>()
+            .init_resource::<PlayerSetup>()
+            .init_resource::<Difficulty>()
+            .init_resource::<PowerUpEffects>()
+            .insert_resource(SpatialGrid::new(GRID_CELL_SIZE))
+            .add_systems(
+                Update,
+                (rebuild_spatial_grid, detect_intersection_player)
+                    .chain()
+                    .after(move_enemies)
+                    .after(lash_frog_tongues)
+                    .after(move_web)
+                    .after(cull_escaped_enemies),
+            )
+            .add_systems(Update, apply_escapes.after(cull_escaped_enemies))
+            .add_systems(Update, toggle_pause.run_if(live_input))
+            .add_systems(Update, toggle_start.run_if(live_input))
+            .add_systems(Update, select_game_mode.run_if(live_input))
             .add_systems(Update, update_time)
+            .add_systems(Update, end_time_attack.after(update_time))
+            .add_systems(Update, update_power_ups)
+            .add_systems(Update, collect_power_ups)
+            .add_systems(Update, render_power_ups)
+            .add_systems(Update, update_difficulty)
             .add_systems(Update, burn_energy)
-            .add_systems(Update, render_score);
+            .add_systems(Update, end_run_without_spiders)
+            .add_systems(Update, render_score)
+            .add_systems(Update, render_mode_hud)
+            .add_systems(Update, render_seed);
     }
 }
 
 fn init(mut commands: Commands, mut next_state: ResMut<NextState<GameState>>) {
     next_state.set(GameState::StartMenu);
     commands.insert_resource(GameResources {
-        energy: 1.,
+        energy: [1.; MAX_PLAYERS],
         score: 0,
         time: 0.,
+        combo: 0,
+        multiplier: 1,
+        combo_timer: 0.,
+        flies: 0,
+        mosquitoes: 0,
+        death: None,
     });
 }
 
@@ -58,73 +357,250 @@ fn update_time(
     time: Res<Time>,
 ) {
     if *game_state.get() == GameState::Active {
-        game_resources.time += time.delta_seconds()
+        game_resources.time += time.delta_seconds();
+        if game_resources.combo > 0 {
+            game_resources.combo_timer -= time.delta_seconds();
+            if game_resources.combo_timer <= 0. {
+                game_resources.break_combo();
+            }
+        }
+    }
+}
+
+fn update_difficulty(
+    curve: Res<DifficultyCurve>,
+    boss_fight: Res<BossFight>,
+    game_resources: Res<GameResources>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    let time = game_resources.time;
+    let mut next = match curve.sample(time) {
+        Some(keyframe) => Difficulty {
+            spawn_interval: keyframe.spawn_interval,
+            speed: keyframe.speed,
+            weights: keyframe.weights,
+            ..default()
+        },
+        None => Difficulty::default(),
+    };
+    match curve.wave_at(time) {
+        Some(WavePhase::Wave(wave)) => {
+            next.spawn_interval = wave.spawn_interval;
+            next.wave = Some(wave.name.clone());
+            next.weights = wave.weights.clone();
+            next.default_weight = 0.;
+        }
+        Some(WavePhase::Breather) => next.spawning = false,
+        None => {}
+    }
+    // the boss has the playfield to itself
+    if boss_fight.active.is_some() {
+        next.spawning = false;
+    }
+    if next.wave.is_some() && next.wave != difficulty.wave {
+        info!(
+            "wave incoming: {}",
+            next.wave.as_deref().unwrap_or_default()
+        );
+    }
+    *difficulty = next;
+}
+
+fn update_power_ups(
+    game_state: Res<State<GameState>>,
+    mut effects: ResMut<PowerUpEffects>,
+    time: Res<Time>,
+) {
+    if *game_state.get() == GameState::Active {
+        effects.tick(time.delta_seconds());
+    }
+}
+
+fn collect_power_ups(
+    mut commands: Commands,
+    power_up_query: Query<(&Transform, &Collider, Entity, &PowerUpEntity)>,
+    player_query: Query<(&Transform, &Collider), SpiderFilter>,
+    definitions: Res<PowerUpDefinitions>,
+    mut effects: ResMut<PowerUpEffects>,
+) {
+    for (player_transform, player_collider) in player_query.iter() {
+        let player_pos = player_transform.translation.truncate();
+        for (transform, collider, entity, power_up) in power_up_query.iter() {
+            if player_collider
+                .contact(player_pos, collider, transform.translation.truncate())
+                .is_none()
+            {
+                continue;
+            }
+            commands.entity(entity).despawn();
+            if let Some(definition) = definitions.power_ups.get(power_up.definition) {
+                info!("picked up {}", definition.name);
+                effects.activate(definition.kind, definition.duration);
+            }
+        }
+    }
+}
+
+pub fn render_power_ups(
+    mut texts: Query<&mut Text, With<PowerUpHud>>,
+    game_state: Res<State<GameState>>,
+    effects: Res<PowerUpEffects>,
+) {
+    for mut text in &mut texts {
+        text.sections[0].value = match *game_state.get() {
+            GameState::Active | GameState::Pause => effects
+                .timers
+                .iter()
+                .map(|(kind, remaining)| format!("{} {:.0}s\n", kind.label(), remaining.ceil()))
+                .collect(),
+            _ => "".into(),
+        };
+    }
+}
+
+// bugs that got away cost or earn whatever their definition says, from every pool
+pub fn apply_escapes(
+    mut escaped: EventReader<EnemyEscaped>,
+    definitions: Res<EnemyDefinitions>,
+    mode: Res<GameMode>,
+    setup: Res<PlayerSetup>,
+    mut game_resources: ResMut<GameResources>,
+) {
+    for event in escaped.read() {
+        if let Some(definition) = definitions.enemies.get(event.definition) {
+            game_resources.score += definition.escaped.score;
+            if mode.burns_energy() {
+                for energy in &mut game_resources.energy[..setup.energy_pools()] {
+                    *energy = (*energy + definition.escaped.energy).max(0.);
+                }
+            }
+            if definition.escaped.breaks_combo {
+                game_resources.break_combo();
+            }
+        }
     }
 }
 
+// every spider burns its own pool, spiders sharing a pool burn it twice as fast; a
+// spider starves once its pool runs dry
+#[allow(clippy::too_many_arguments)]
 pub fn burn_energy(
+    mut commands: Commands,
+    spider_query: Query<(Entity, &Spider)>,
     game_state: Res<State<GameState>>,
-    mut next_game_state: ResMut<NextState<GameState>>,
     mut game_resources: ResMut<GameResources>,
-    controller_state: Res<PlayerControllerState>,
+    controllers: Res<PlayerControllers>,
+    effects: Res<PowerUpEffects>,
+    mode: Res<GameMode>,
+    setup: Res<PlayerSetup>,
     time: Res<Time>,
 ) {
-    if *game_state.get() == GameState::Active {
-        if game_resources.energy <= 0. {
-            next_game_state.set(GameState::GameOver);
+    if *game_state.get() != GameState::Active || !mode.burns_energy() {
+        return;
+    }
+    for (entity, spider) in &spider_query {
+        let pool = setup.energy_pool(spider.player);
+        let controller_state = controllers.get(spider.player);
+        if game_resources.energy[pool] <= 0. {
+            // the thread goes with it in despawn_orphans
+            commands.entity(entity).despawn();
+            game_resources.kill(DeathCause::Starved);
         } else if game_resources.time >= 5. {
-            if controller_state.is_moving() {
-                game_resources.energy -= time.delta_seconds() * MOVING_ENERGY_BURNING_RATE;
+            // haste makes getting around as cheap as hanging still
+            let rate = if effects.is_active(PowerUpKind::Haste) {
+                IDLE_ENERGY_BURNING_RATE
+            } else if controller_state.is_moving() {
+                MOVING_ENERGY_BURNING_RATE
             } else if controller_state.is_boosting() {
-                game_resources.energy -= time.delta_seconds() * SPRINTING_ENERGY_BURNING_RATE;
+                // a half pressed trigger burns halfway between idling and sprinting
+                IDLE_ENERGY_BURNING_RATE
+                    + (SPRINTING_ENERGY_BURNING_RATE - IDLE_ENERGY_BURNING_RATE)
+                        * controller_state.boost()
             } else {
-                game_resources.energy -= time.delta_seconds() * IDLE_ENERGY_BURNING_RATE;
-            }
+                IDLE_ENERGY_BURNING_RATE
+            };
+            game_resources.energy[pool] -= time.delta_seconds() * rate;
         }
     }
 }
 
+// the run goes on as long as one spider is left hanging
+fn end_run_without_spiders(
+    spider_query: Query<(), With<Spider>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if *game_state.get() == GameState::Active && spider_query.is_empty() {
+        next_game_state.set(GameState::GameOver);
+    }
+}
+
+// time attack runs end on the clock, whatever energy is left
+fn end_time_attack(
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut game_resources: ResMut<GameResources>,
+    mode: Res<GameMode>,
+) {
+    if *game_state.get() == GameState::Active
+        && *mode == GameMode::TimeAttack
+        && game_resources.time >= TIME_ATTACK_DURATION
+    {
+        game_resources.time = TIME_ATTACK_DURATION;
+        next_game_state.set(GameState::GameOver);
+    }
+}
+
+// left and right on the start menu flip through the modes, up and down through the
+// player setups
+pub fn select_game_mode(
+    input: ActionInput,
+    game_state: Res<State<GameState>>,
+    mut mode: ResMut<GameMode>,
+    mut setup: ResMut<PlayerSetup>,
+) {
+    if *game_state.get() != GameState::StartMenu {
+        return;
+    }
+    let step = |negative: Action, positive: Action| {
+        isize::from(input.just_pressed(positive)) - isize::from(input.just_pressed(negative))
+    };
+    let mode_step = step(Action::MoveLeft, Action::MoveRight);
+    if mode_step != 0 {
+        *mode = mode.cycle(mode_step);
+    }
+    let setup_step = step(Action::MoveUp, Action::MoveDown);
+    if setup_step != 0 {
+        *setup = setup.cycle(setup_step);
+    }
+}
+
 pub fn toggle_pause(
-    mut keys: ResMut<Input<KeyCode>>,
-    mut gamepad_events: EventReader<GamepadButtonChangedEvent>,
+    mut input: ActionInput,
     game_state: Res<State<GameState>>,
     mut next_game_state: ResMut<NextState<GameState>>,
 ) {
-    let start_button_pressed = gamepad_events
-        .read()
-        .find(|e| e.button_type == GamepadButtonType::Start && e.value > 0.)
-        .is_some();
-    if !!keys.just_pressed(KeyCode::Escape) || start_button_pressed {
-        next_game_state.set(match *game_state.get() {
-            GameState::Active => GameState::Pause,
-            GameState::Pause => GameState::Active,
-            _ => *game_state.get(),
-        });
-        keys.reset(KeyCode::Escape);
+    let next = match *game_state.get() {
+        GameState::Active => GameState::Pause,
+        GameState::Pause => GameState::Active,
+        _ => return,
+    };
+    if input.just_pressed(Action::Pause) {
+        next_game_state.set(next);
+        input.consume(Action::Pause);
     }
 }
 
 pub fn toggle_start(
-    mut keys: ResMut<Input<KeyCode>>,
-    // mut click_events: EventReader<MouseButtonInput>,
-    mut gamepad_events: EventReader<GamepadButtonChangedEvent>,
+    mut input: ActionInput,
     game_state: Res<State<GameState>>,
     mut next_game_state: ResMut<NextState<GameState>>,
     mut game_resources: ResMut<GameResources>,
 ) {
-    let start_button_pressed = gamepad_events
-        .read()
-        .find(|e| e.button_type == GamepadButtonType::Start && e.value > 0.)
-        .is_some();
-    // let clicked = click_events.read().next().is_some();
-    if !!keys.just_pressed(KeyCode::Space) || start_button_pressed {
-        if *game_state.get() == GameState::StartMenu {
-            next_game_state.set(GameState::Init);
-            game_resources.score = 0;
-            game_resources.time = 0.;
-            game_resources.energy = 1.;
-        }
-        keys.reset(KeyCode::Escape);
+    if *game_state.get() == GameState::StartMenu && input.just_pressed(Action::Confirm) {
+        next_game_state.set(GameState::Init);
+        game_resources.reset();
+        input.consume(Action::Confirm);
     }
 }
 
@@ -136,6 +612,12 @@ pub fn render_score(
     let score: &str = &game_resources.score.to_string();
     for mut text in &mut texts {
         match *game_state.get() {
+            GameState::Active | GameState::Pause if game_resources.multiplier > 1 => {
+                text.sections[0].value = format!(
+                    "{} x{} ({} combo)",
+                    score, game_resources.multiplier, game_resources.combo
+                );
+            }
             GameState::Active | GameState::GameOver | GameState::Pause => {
                 text.sections[0].value = score.into();
             }
@@ -144,74 +626,193 @@ pub fn render_score(
     }
 }
 
+pub fn render_mode_hud(
+    mut texts: Query<&mut Text, With<ModeHud>>,
+    game_state: Res<State<GameState>>,
+    game_resources: Res<GameResources>,
+    mode: Res<GameMode>,
+    setup: Res<PlayerSetup>,
+) {
+    for mut text in &mut texts {
+        text.sections[0].value = match (*game_state.get(), *mode) {
+            (GameState::StartMenu, mode) => format!("< {} >\n{}", mode.label(), setup.label()),
+            (GameState::Active | GameState::Pause, GameMode::TimeAttack) => format!(
+                "{:.0}s",
+                (TIME_ATTACK_DURATION - game_resources.time).max(0.).ceil()
+            ),
+            (GameState::Active | GameState::Pause, GameMode::Zen) => "zen".into(),
+            _ => "".into(),
+        };
+    }
+}
+
+pub fn render_seed(
+    mut texts: Query<&mut Text, With<Seed>>,
+    game_state: Res<State<GameState>>,
+    game_resources: Res<GameResources>,
+    rng: Res<GameRng>,
+) {
+    for mut text in &mut texts {
+        match *game_state.get() {
+            GameState::GameOver | GameState::StartMenu if game_resources.time > 0. => {
+                text.sections[0].value = format!("seed {}", rng.seed());
+            }
+            _ => text.sections[0].value = "".into(),
+        }
+    }
+}
+
+// everything a bug can touch this frame: its body, the path since last frame and its tongue
+pub fn rebuild_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    enemy_query: Query<(&Transform, &Collider, Entity, &EnemyEntity, Option<&Frog>)>,
+) {
+    grid.clear();
+    for (enemy_transform, enemy_collider, enemy_entity, enemy, frog) in enemy_query.iter() {
+        let enemy_pos = enemy_transform.translation.truncate();
+        let mut bounds = enemy_collider
+            .bounds(enemy_pos)
+            .union(enemy_collider.bounds(enemy.previous));
+        if let Some(tongue) = frog.and_then(|f| f.tongue_collider(enemy_transform.translation)) {
+            bounds = bounds.union(tongue.bounds(Vec2::ZERO));
+        }
+        grid.insert(enemy_entity, bounds);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn detect_intersection_player(
     mut commands: Commands,
-    enemy_query: Query<(&Transform, &TextureAtlasSprite, Entity, &EnemyEntity), With<EnemyEntity>>,
-    player_query: Query<
-        (&Transform, &TextureAtlasSprite, Entity),
-        (With<PlayerEntity>, Without<PlayerAttached>),
-    >,
-    web_query: Query<(&Transform, &Sprite, Entity), (With<PlayerEntity>, With<PlayerAttached>)>,
-    mut next_game_state: ResMut<NextState<GameState>>,
+    enemy_query: Query<(&Transform, &Collider, Entity, &EnemyEntity, Option<&Frog>)>,
+    player_query: Query<(&Transform, &Collider, &Spider), SpiderFilter>,
+    web_query: Query<(&Web, &PlayerAttached, Entity)>,
+    grid: Res<SpatialGrid>,
+    controllers: Res<PlayerControllers>,
+    mut effects: ResMut<PowerUpEffects>,
     mut game_resources: ResMut<GameResources>,
+    definitions: Res<EnemyDefinitions>,
+    mode: Res<GameMode>,
+    setup: Res<PlayerSetup>,
 ) {
-    for player in player_query.iter() {
-        let player_pos = player.0.translation;
-        let player_size = player.1.custom_size.unwrap_or_default();
-        let player_entity = player.2;
-        for web in web_query.iter() {
-            let web_pos = web.0.translation;
-            let web_size = web.1.custom_size.unwrap_or_default();
-            let web_entity = web.2;
-            for enemy in enemy_query.iter() {
-                let enemy_pos = enemy.0.translation;
-                let enemy_size = enemy.1.custom_size.unwrap_or_default();
-                let enemy_entity = enemy.2;
-                match collide(player_pos, player_size, enemy_pos, enemy_size) {
-                    Some(Collision::Bottom) => {
-                        commands.entity(player_entity).despawn();
-                        commands.entity(web_entity).despawn();
-                        next_game_state.set(GameState::GameOver);
-                    }
-                    Some(_collision) => {
+    let points = if effects.is_active(PowerUpKind::DoublePoints) {
+        2
+    } else {
+        1
+    };
+    let mut nearby = Vec::new();
+    // bugs already caught or blocked this frame, so one touching both webs counts once
+    let mut gone = HashSet::new();
+    // a bug only kills the spider it touches, the other one keeps going
+    for (web, attached, web_entity) in web_query.iter() {
+        let player_entity = attached.spider;
+        let Ok((player_transform, player_collider, spider)) = player_query.get(player_entity)
+        else {
+            continue;
+        };
+        let player_pos = player_transform.translation.truncate();
+        let pool = setup.energy_pool(spider.player);
+        let threads: Vec<Collider> = web.segments().collect();
+        // only bugs sharing a cell with the spider or the thread can touch either
+        nearby.clear();
+        grid.query(player_collider.bounds(player_pos), &mut nearby);
+        for thread in &threads {
+            grid.query(thread.bounds(Vec2::ZERO), &mut nearby);
+        }
+        for (enemy_transform, enemy_collider, enemy_entity, enemy, frog) in
+            enemy_query.iter_many(&nearby)
+        {
+            if gone.contains(&enemy_entity) {
+                continue;
+            }
+            let enemy_pos = enemy_transform.translation.truncate();
+            let breaks_web = definitions
+                .enemies
+                .get(enemy.definition)
+                .is_some_and(|definition| definition.breaks_web);
+            let name = || {
+                definitions
+                    .enemies
+                    .get(enemy.definition)
+                    .map_or("bug", |definition| definition.name.as_str())
+                    .to_string()
+            };
+            // zen mode takes the bite out of the bugs that snap the thread
+            let harmless = breaks_web && *mode == GameMode::Zen;
+            // a frog tongue eats the spider or snaps the thread above it
+            if let Some(tongue) = frog.and_then(|f| f.tongue_collider(enemy_transform.translation))
+            {
+                let eaten = tongue
+                    .contact(Vec2::ZERO, player_collider, player_pos)
+                    .is_some();
+                let snapped = threads
+                    .iter()
+                    .any(|thread| tongue.contact(Vec2::ZERO, thread, Vec2::ZERO).is_some());
+                if eaten || snapped {
+                    // the shield blocks a tongue like any other hit and sends the frog off
+                    if effects.is_active(PowerUpKind::Shield) {
+                        effects.consume(PowerUpKind::Shield);
+                        game_resources.break_combo();
                         commands.entity(enemy_entity).despawn();
-                        match enemy.3.enemy_type {
-                            EnemyType::FLY => {
-                                game_resources.score += 1;
-                                game_resources.energy += match game_resources.energy {
-                                    e if (0.0..0.3).contains(&e) => 0.3,
-                                    e if (0.3..0.7).contains(&e) => 0.2,
-                                    e if (0.7..0.9).contains(&e) => 0.1,
-                                    e if (0.9..0.99).contains(&e) => 0.01,
-                                    _ => 0.,
-                                }
-                            }
-                            EnemyType::MOSQUITO => {
-                                game_resources.score += 2;
-                                game_resources.energy += match game_resources.energy {
-                                    e if (0.0..0.3).contains(&e) => 0.6,
-                                    e if (0.3..0.6).contains(&e) => 0.4,
-                                    e if (0.6..0.8).contains(&e) => 0.2,
-                                    e if (0.8..0.9).contains(&e) => 0.1,
-                                    _ => 0.,
-                                }
-                            }
-                        }
+                        gone.insert(enemy_entity);
+                        continue;
                     }
-                    _ => {}
-                };
-                match enemy.3.enemy_type {
-                    EnemyType::MOSQUITO => {
-                        match collide(web_pos, web_size, enemy_pos, enemy_size) {
-                            Some(_collision) => {
-                                commands.entity(web_entity).despawn();
-                                commands.entity(player_entity).despawn();
-                                next_game_state.set(GameState::GameOver);
-                            }
-                            _ => {}
-                        }
+                    commands.entity(player_entity).despawn();
+                    commands.entity(web_entity).despawn();
+                    game_resources.kill(if eaten {
+                        DeathCause::EatenBy(name())
+                    } else {
+                        DeathCause::SnappedThread(name())
+                    });
+                    break;
+                }
+            }
+            match player_collider.contact(player_pos, enemy_collider, enemy_pos) {
+                // the normal points from the spider to the bug
+                Some(contact) if contact.normal.y < HIT_FROM_BELOW && !harmless => {
+                    commands.entity(player_entity).despawn();
+                    commands.entity(web_entity).despawn();
+                    game_resources.kill(DeathCause::HitFromBelow(name()));
+                    break;
+                }
+                Some(_contact) => {
+                    commands.entity(enemy_entity).despawn();
+                    gone.insert(enemy_entity);
+                    match enemy.enemy_type {
+                        EnemyType::FLY => game_resources.flies += 1,
+                        EnemyType::MOSQUITO => game_resources.mosquitoes += 1,
+                        EnemyType::FROG => {}
+                    }
+                    if let Some(definition) = definitions.enemies.get(enemy.definition) {
+                        game_resources.catch(
+                            definition.score * points,
+                            controllers.get(spider.player).is_boosting(),
+                        );
+                        let energy = game_resources.energy[pool];
+                        game_resources.energy[pool] += definition.energy_gain(energy);
+                    }
+                }
+                None => {}
+            };
+            // swept from the bug's last position so fast bugs can't skip over the thread
+            if breaks_web && !harmless {
+                let swept = enemy_collider.swept(enemy.previous, enemy_pos);
+                if threads
+                    .iter()
+                    .any(|thread| swept.contact(Vec2::ZERO, thread, Vec2::ZERO).is_some())
+                {
+                    // the shield takes the hit instead of the thread, once, but a hit still
+                    // breaks the combo
+                    if effects.is_active(PowerUpKind::Shield) {
+                        effects.consume(PowerUpKind::Shield);
+                        game_resources.break_combo();
+                        commands.entity(enemy_entity).despawn();
+                        gone.insert(enemy_entity);
+                        continue;
                     }
-                    _ => {}
+                    commands.entity(web_entity).despawn();
+                    commands.entity(player_entity).despawn();
+                    game_resources.kill(DeathCause::SnappedThread(name()));
+                    break;
                 }
             }
         }
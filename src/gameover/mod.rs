@@ -0,0 +1,154 @@
+use crate::{
+    controllers::{Action, ActionInput},
+    game::{GameMode, GameResources, GameRng, GameState},
+    highscores::HighScoreTables,
+    menu::{show_menu, spawn_menu, MenuButton, MenuEntry, MenuSelection},
+    replay::live_input,
+};
+use bevy::prelude::*;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOverEntry {
+    Retry,
+    Menu,
+}
+impl MenuEntry for GameOverEntry {
+    const ALL: &'static [GameOverEntry] = &[GameOverEntry::Retry, GameOverEntry::Menu];
+    fn label(&self) -> &'static str {
+        match self {
+            GameOverEntry::Retry => "retry",
+            GameOverEntry::Menu => "menu",
+        }
+    }
+}
+#[derive(Resource, Default)]
+pub struct GameOverScreen {
+    selection: MenuSelection,
+}
+#[derive(Component)]
+pub struct GameOverOverlay;
+#[derive(Component)]
+pub struct GameOverSummary;
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameOverScreen>()
+            .add_systems(Startup, spawn_game_over_screen)
+            .add_systems(OnEnter(GameState::GameOver), reset_game_over_screen)
+            .add_systems(Update, update_game_over_screen.run_if(live_input))
+            .add_systems(Update, render_game_over_screen);
+    }
+}
+
+fn reset_game_over_screen(mut screen: ResMut<GameOverScreen>) {
+    screen.selection.reset();
+}
+
+// the game boots into GameOver, a run that never started has nothing to sum up
+fn has_run(game_resources: &GameResources) -> bool {
+    game_resources.time > 0.
+}
+
+// up and down pick, confirm or a click leaves, the pause action goes straight to the menu
+fn update_game_over_screen(
+    mut input: ActionInput,
+    mut screen: ResMut<GameOverScreen>,
+    button_query: Query<(&Interaction, &MenuButton<GameOverEntry>), Changed<Interaction>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut game_resources: ResMut<GameResources>,
+) {
+    if *game_state.get() != GameState::GameOver || !has_run(&game_resources) {
+        return;
+    }
+    let mut chosen = screen.selection.navigate(&mut input);
+    if chosen.is_none() && input.just_pressed(Action::Pause) {
+        chosen = Some(GameOverEntry::Menu);
+        input.consume(Action::Pause);
+    }
+    if let Some(entry) = screen.selection.click(&button_query) {
+        chosen = Some(entry);
+    }
+    // leaving GameOver clears the old run away
+    match chosen {
+        Some(GameOverEntry::Retry) => {
+            game_resources.reset();
+            next_game_state.set(GameState::Init);
+        }
+        Some(GameOverEntry::Menu) => next_game_state.set(GameState::StartMenu),
+        None => {}
+    }
+}
+
+fn spawn_game_over_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font_size: 24.,
+        color: Color::WHITE,
+        font: asset_server.load("fonts/bigblueterm.ttf"),
+    };
+    let summary = (
+        TextBundle::from_section("", style.clone()).with_text_alignment(TextAlignment::Center),
+        GameOverSummary,
+    );
+    spawn_menu::<GameOverEntry>(&mut commands, GameOverOverlay, summary, style);
+}
+
+fn summary(
+    game_resources: &GameResources,
+    mode: GameMode,
+    high_score_tables: &HighScoreTables,
+    seed: u64,
+) -> String {
+    // the overlay hides the seed hud, so the seed to share the run by goes here
+    let mut summary = format!(
+        "game over\n\nscore {}\ntime {:.1}s\nseed {}\ncaught {} flies, {} mosquitoes\n",
+        game_resources.score,
+        game_resources.time,
+        seed,
+        game_resources.flies,
+        game_resources.mosquitoes
+    );
+    summary += &match &game_resources.death {
+        Some(death) => death.describe(),
+        // time attack is the only way to end a run without dying
+        None if mode == GameMode::TimeAttack => "time's up".into(),
+        None => "".into(),
+    };
+    let rank = high_score_tables
+        .tables
+        .get(&mode)
+        .and_then(|high_scores| high_scores.latest);
+    summary += &match rank {
+        Some(0) => "\n\nnew high score!".into(),
+        Some(rank) => format!("\n\n#{} on the high scores", rank + 1),
+        None => "".into(),
+    };
+    summary
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_game_over_screen(
+    mut overlay_query: Query<&mut Visibility, With<GameOverOverlay>>,
+    mut texts: Query<&mut Text, With<GameOverSummary>>,
+    mut button_query: Query<(&MenuButton<GameOverEntry>, &mut BackgroundColor)>,
+    screen: Res<GameOverScreen>,
+    game_resources: Res<GameResources>,
+    high_score_tables: Res<HighScoreTables>,
+    mode: Res<GameMode>,
+    rng: Res<GameRng>,
+    game_state: Res<State<GameState>>,
+) {
+    let open = *game_state.get() == GameState::GameOver && has_run(&game_resources);
+    show_menu(
+        open,
+        &screen.selection,
+        &mut overlay_query,
+        &mut button_query,
+    );
+    if !open {
+        return;
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = summary(&game_resources, *mode, &high_score_tables, rng.seed());
+    }
+}
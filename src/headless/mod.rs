@@ -0,0 +1,51 @@
+use crate::{
+    game::{GameResources, GameState},
+    replay::{deterministic_update, ReplayPlayback},
+};
+use bevy::{app::AppExit, prelude::*, time::TimeUpdateStrategy};
+use std::time::Duration;
+pub const HEADLESS_TIMESTEP: f32 = 1. / 60.;
+#[derive(Resource, Default)]
+struct HeadlessRun {
+    started: bool,
+}
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        // a seeded headless run plays out the same way every time
+        deterministic_update(app);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            HEADLESS_TIMESTEP,
+        )))
+        .init_resource::<HeadlessRun>()
+        .add_systems(Update, drive_headless_run);
+    }
+}
+
+// skips the start menu, plays a single run and exits as soon as it is over, a replay
+// starts its run by itself
+fn drive_headless_run(
+    mut run: ResMut<HeadlessRun>,
+    playback: Option<Res<ReplayPlayback>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut game_resources: ResMut<GameResources>,
+    mut exit: EventWriter<AppExit>,
+) {
+    match *game_state.get() {
+        GameState::StartMenu if !run.started && playback.is_none() => {
+            next_game_state.set(GameState::Init);
+            game_resources.reset();
+        }
+        GameState::Init => run.started = true,
+        GameState::GameOver if run.started => {
+            info!(
+                "run finished: score {}, time {:.2}s",
+                game_resources.score, game_resources.time
+            );
+            exit.send(AppExit);
+        }
+        _ => {}
+    }
+}
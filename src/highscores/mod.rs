@@ -0,0 +1,185 @@
+use crate::{
+    game::{GameMode, GameResources, GameRng, GameState},
+    replay::live_input,
+    storage,
+};
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+pub const HIGH_SCORE_COUNT: usize = 10;
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighScore {
+    pub score: u32,
+    pub time: f32,
+    // seconds since the unix epoch
+    pub date: u64,
+    pub seed: u64,
+}
+// best runs first, never more than HIGH_SCORE_COUNT of them
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+    // where the last run landed, only for highlighting it
+    #[serde(skip)]
+    pub latest: Option<usize>,
+}
+// endless keeps the table name from before there were modes
+fn table_name(mode: GameMode) -> String {
+    match mode {
+        GameMode::Endless => "highscores".into(),
+        mode => format!("highscores-{}", mode.key()),
+    }
+}
+impl HighScores {
+    pub fn load(mode: GameMode) -> Result<Self, Box<dyn Error>> {
+        match storage::read(&table_name(mode))? {
+            Some(table) => Ok(ron::from_str(&table)?),
+            None => Ok(Self::default()),
+        }
+    }
+    pub fn save(&self, mode: GameMode) -> Result<(), Box<dyn Error>> {
+        storage::write(&table_name(mode), &ron::to_string(self)?)
+    }
+    // returns the rank the run made it to, if it made it at all
+    pub fn submit(&mut self, entry: HighScore) -> Option<usize> {
+        // ties go to the longer run, then to the older one
+        let rank = self
+            .entries
+            .iter()
+            .position(|e| (entry.score, entry.time) > (e.score, e.time))
+            .unwrap_or(self.entries.len());
+        self.latest = None;
+        if rank >= HIGH_SCORE_COUNT {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+        self.latest = Some(rank);
+        self.latest
+    }
+}
+// one table per mode, their scores don't compare
+#[derive(Resource, Default)]
+pub struct HighScoreTables {
+    pub tables: HashMap<GameMode, HighScores>,
+}
+#[derive(Component)]
+pub struct HighScoreTable;
+pub struct HighScoresPlugin;
+
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        let tables = GameMode::ALL
+            .into_iter()
+            .map(|mode| {
+                let high_scores = HighScores::load(mode).unwrap_or_else(|err| {
+                    error!("could not load {} high scores: {}", mode.label(), err);
+                    HighScores::default()
+                });
+                (mode, high_scores)
+            })
+            .collect();
+        app.insert_resource(HighScoreTables { tables })
+            .add_systems(Startup, spawn_high_score_table)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                record_high_score.run_if(live_input),
+            )
+            .add_systems(Update, render_high_score_table);
+    }
+}
+
+fn record_high_score(
+    mut high_score_tables: ResMut<HighScoreTables>,
+    game_resources: Res<GameResources>,
+    rng: Res<GameRng>,
+    mode: Res<GameMode>,
+) {
+    // the game boots into GameOver before any run was played
+    if game_resources.time <= 0. {
+        return;
+    }
+    let high_scores = high_score_tables.tables.entry(*mode).or_default();
+    let rank = high_scores.submit(HighScore {
+        score: game_resources.score,
+        time: game_resources.time,
+        date: storage::now(),
+        seed: rng.seed(),
+    });
+    if let Some(rank) = rank {
+        info!("new {} high score, rank {}", mode.label(), rank + 1);
+        if let Err(err) = high_scores.save(*mode) {
+            error!("could not save high scores: {}", err);
+        }
+    }
+}
+
+fn spawn_high_score_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font_size: 16.,
+        color: Color::WHITE,
+        font: asset_server.load("fonts/bigblueterm.ttf"),
+    };
+    commands.spawn((
+        TextBundle::from_sections([TextSection::new("", style)]).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(15.),
+            left: Val::Px(25.),
+            ..default()
+        }),
+        HighScoreTable,
+    ));
+}
+
+fn render_high_score_table(
+    mut texts: Query<&mut Text, With<HighScoreTable>>,
+    high_score_tables: Res<HighScoreTables>,
+    game_state: Res<State<GameState>>,
+    mode: Res<GameMode>,
+) {
+    let empty = HighScores::default();
+    let high_scores = high_score_tables.tables.get(&*mode).unwrap_or(&empty);
+    for mut text in &mut texts {
+        text.sections[0].value = match *game_state.get() {
+            GameState::StartMenu | GameState::GameOver if !high_scores.entries.is_empty() => {
+                let mut table = format!("high scores, {}\n", mode.label());
+                for (rank, entry) in high_scores.entries.iter().enumerate() {
+                    let marker = if high_scores.latest == Some(rank) {
+                        ">"
+                    } else {
+                        " "
+                    };
+                    table += &format!(
+                        "{}{:>2}. {:>6} {:>6.1}s {}\n",
+                        marker,
+                        rank + 1,
+                        entry.score,
+                        entry.time,
+                        format_date(entry.date)
+                    );
+                }
+                table
+            }
+            _ => "".into(),
+        };
+    }
+}
+
+// yyyy-mm-dd in utc, from the days since the epoch
+fn format_date(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,273 @@
+use crate::{
+    game::{GameMode, GameResources, GameState},
+    replay::{keep_replays, live_input, record_replay, RunRecorded},
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Mutex,
+};
+pub const LEADERBOARD_SIZE: usize = 10;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub player: String,
+    // runs only compete with runs of the same mode
+    #[serde(default)]
+    pub mode: GameMode,
+    pub score: u32,
+    pub time: f32,
+    // the seed and the hash of the replay kept under it, played back the replay has to
+    // come out at the same score
+    pub seed: u64,
+    // hex, javascript would lose precision on a plain number
+    pub replay_hash: String,
+}
+pub type LeaderboardResult<T> = Result<T, String>;
+pub type LeaderboardCallback<T> = Box<dyn FnOnce(LeaderboardResult<T>) + Send>;
+// backends answer through callbacks so a slow server never holds up a frame
+pub trait Leaderboard: Send + Sync {
+    fn submit(&self, entry: LeaderboardEntry, done: LeaderboardCallback<()>);
+    fn top(&self, mode: GameMode, count: usize, done: LeaderboardCallback<Vec<LeaderboardEntry>>);
+}
+// keeps the runs of this session only, used when no server is configured
+#[derive(Default)]
+pub struct InMemoryLeaderboard {
+    entries: Mutex<Vec<LeaderboardEntry>>,
+}
+impl Leaderboard for InMemoryLeaderboard {
+    fn submit(&self, entry: LeaderboardEntry, done: LeaderboardCallback<()>) {
+        match self.entries.lock() {
+            Ok(mut entries) => {
+                let rank = entries
+                    .iter()
+                    .position(|e| entry.score > e.score)
+                    .unwrap_or(entries.len());
+                entries.insert(rank, entry);
+                done(Ok(()));
+            }
+            Err(err) => done(Err(err.to_string())),
+        }
+    }
+    fn top(&self, mode: GameMode, count: usize, done: LeaderboardCallback<Vec<LeaderboardEntry>>) {
+        match self.entries.lock() {
+            Ok(entries) => done(Ok(entries
+                .iter()
+                .filter(|entry| entry.mode == mode)
+                .take(count)
+                .cloned()
+                .collect())),
+            Err(err) => done(Err(err.to_string())),
+        }
+    }
+}
+// POST {url}/runs with an entry, GET {url}/runs?mode=m&limit=n for the best runs of a
+// mode, json both ways
+pub struct RestLeaderboard {
+    url: String,
+}
+impl RestLeaderboard {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+fn check_response(response: ehttp::Result<ehttp::Response>) -> LeaderboardResult<ehttp::Response> {
+    let response = response?;
+    if response.ok {
+        Ok(response)
+    } else {
+        Err(format!("{} {}", response.status, response.status_text))
+    }
+}
+impl Leaderboard for RestLeaderboard {
+    fn submit(&self, entry: LeaderboardEntry, done: LeaderboardCallback<()>) {
+        match ehttp::Request::json(format!("{}/runs", self.url), &entry) {
+            Ok(request) => ehttp::fetch(request, move |response| {
+                done(check_response(response).map(|_| ()))
+            }),
+            Err(err) => done(Err(err.to_string())),
+        }
+    }
+    fn top(&self, mode: GameMode, count: usize, done: LeaderboardCallback<Vec<LeaderboardEntry>>) {
+        let request = ehttp::Request::get(format!(
+            "{}/runs?mode={}&limit={}",
+            self.url,
+            mode.key(),
+            count
+        ));
+        ehttp::fetch(request, move |response| {
+            done(
+                check_response(response)
+                    .and_then(|response| response.json().map_err(|err| err.to_string())),
+            )
+        });
+    }
+}
+enum LeaderboardMessage {
+    Submitted(LeaderboardResult<()>),
+    Top(GameMode, LeaderboardResult<Vec<LeaderboardEntry>>),
+}
+#[derive(Resource)]
+pub struct LeaderboardClient {
+    backend: Box<dyn Leaderboard>,
+    player: String,
+    sender: Sender<LeaderboardMessage>,
+    // callbacks may run on another thread, answers are picked up once a frame
+    receiver: Mutex<Receiver<LeaderboardMessage>>,
+    pub top: Vec<LeaderboardEntry>,
+}
+impl LeaderboardClient {
+    pub fn new(backend: Box<dyn Leaderboard>, player: String) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            backend,
+            player,
+            sender,
+            receiver: Mutex::new(receiver),
+            top: Vec::new(),
+        }
+    }
+    pub fn submit(&self, mode: GameMode, score: u32, time: f32, seed: u64, replay_hash: u64) {
+        let sender = self.sender.clone();
+        self.backend.submit(
+            LeaderboardEntry {
+                player: self.player.clone(),
+                mode,
+                score,
+                time,
+                seed,
+                replay_hash: format!("{:016x}", replay_hash),
+            },
+            Box::new(move |result| {
+                let _ = sender.send(LeaderboardMessage::Submitted(result));
+            }),
+        );
+    }
+    pub fn refresh(&self, mode: GameMode) {
+        let sender = self.sender.clone();
+        self.backend.top(
+            mode,
+            LEADERBOARD_SIZE,
+            Box::new(move |result| {
+                let _ = sender.send(LeaderboardMessage::Top(mode, result));
+            }),
+        );
+    }
+}
+#[derive(Component)]
+pub struct LeaderboardTable;
+pub struct LeaderboardPlugin {
+    // without a server url the board only holds this session's runs
+    pub url: Option<String>,
+    pub player: String,
+}
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        let backend: Box<dyn Leaderboard> = match &self.url {
+            Some(url) => Box::new(RestLeaderboard::new(url)),
+            None => Box::<InMemoryLeaderboard>::default(),
+        };
+        // every finished run is submitted, so every run has to be verifiable
+        keep_replays(app);
+        app.insert_resource(LeaderboardClient::new(backend, self.player.clone()))
+            .add_systems(Startup, spawn_leaderboard_table)
+            .add_systems(OnEnter(GameState::StartMenu), refresh_leaderboard)
+            .add_systems(
+                Update,
+                refresh_leaderboard.run_if(resource_changed::<GameMode>()),
+            )
+            .add_systems(
+                PostUpdate,
+                submit_run.after(record_replay).run_if(live_input),
+            )
+            .add_systems(Update, receive_leaderboard)
+            .add_systems(Update, render_leaderboard_table);
+    }
+}
+
+fn refresh_leaderboard(mut client: ResMut<LeaderboardClient>, mode: Res<GameMode>) {
+    // the old mode's runs shouldn't show up under the new mode's title
+    if mode.is_changed() {
+        client.top.clear();
+    }
+    client.refresh(*mode);
+}
+
+fn submit_run(
+    mut recorded: EventReader<RunRecorded>,
+    client: Res<LeaderboardClient>,
+    game_resources: Res<GameResources>,
+    mode: Res<GameMode>,
+) {
+    for run in recorded.read() {
+        client.submit(
+            *mode,
+            game_resources.score,
+            game_resources.time,
+            run.seed,
+            run.hash,
+        );
+    }
+}
+
+fn receive_leaderboard(mut client: ResMut<LeaderboardClient>, mode: Res<GameMode>) {
+    let messages: Vec<LeaderboardMessage> = match client.receiver.lock() {
+        Ok(receiver) => receiver.try_iter().collect(),
+        Err(_) => return,
+    };
+    for message in messages {
+        match message {
+            LeaderboardMessage::Submitted(Ok(())) => client.refresh(*mode),
+            LeaderboardMessage::Submitted(Err(err)) => error!("could not submit run: {}", err),
+            // answers for a mode that is no longer selected are stale
+            LeaderboardMessage::Top(top_mode, _) if top_mode != *mode => {}
+            LeaderboardMessage::Top(_, Ok(top)) => client.top = top,
+            LeaderboardMessage::Top(_, Err(err)) => error!("could not fetch leaderboard: {}", err),
+        }
+    }
+}
+
+fn spawn_leaderboard_table(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font_size: 16.,
+        color: Color::WHITE,
+        font: asset_server.load("fonts/bigblueterm.ttf"),
+    };
+    commands.spawn((
+        TextBundle::from_sections([TextSection::new("", style)]).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(15.),
+            right: Val::Px(25.),
+            ..default()
+        }),
+        LeaderboardTable,
+    ));
+}
+
+fn render_leaderboard_table(
+    mut texts: Query<&mut Text, With<LeaderboardTable>>,
+    client: Res<LeaderboardClient>,
+    game_state: Res<State<GameState>>,
+    mode: Res<GameMode>,
+) {
+    for mut text in &mut texts {
+        text.sections[0].value = match *game_state.get() {
+            GameState::StartMenu if !client.top.is_empty() => {
+                let mut table = format!("leaderboard, {}\n", mode.label());
+                for (rank, entry) in client.top.iter().enumerate() {
+                    table += &format!(
+                        "{:>2}. {:<10.10} {:>6}\n",
+                        rank + 1,
+                        entry.player,
+                        entry.score
+                    );
+                }
+                table
+            }
+            _ => "".into(),
+        };
+    }
+}
@@ -0,0 +1,42 @@
+use bevy::{app::PluginGroupBuilder, prelude::*};
+
+pub mod benchmark;
+pub mod bosses;
+pub mod collision;
+pub mod controllers;
+pub mod definitions;
+pub mod entities;
+pub mod game;
+pub mod gameover;
+pub mod headless;
+pub mod highscores;
+pub mod leaderboard;
+pub mod menu;
+pub mod pause;
+pub mod replay;
+pub mod settings;
+pub mod storage;
+pub mod touch;
+pub mod ui;
+
+// everything a run needs with or without a window, the menus and hud go on top
+#[derive(Default)]
+pub struct GamePlugins {
+    pub record_to: Option<String>,
+    pub replay_from: Option<String>,
+}
+
+impl PluginGroup for GamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(definitions::DefinitionsPlugin)
+            .add(entities::EntitiesPlugin)
+            .add(controllers::ControllersPlugin)
+            .add(game::GamePlugin)
+            .add(bosses::BossesPlugin)
+            .add(replay::ReplayPlugin {
+                record_to: self.record_to,
+                replay_from: self.replay_from,
+            })
+    }
+}
@@ -1,13 +1,32 @@
-use bevy::prelude::*;
+use bevy::{input::InputPlugin, prelude::*};
+use stupid_spooder_game::{
+    benchmark, game, gameover, headless, highscores, leaderboard, pause, settings, touch, ui,
+    GamePlugins,
+};
 
-mod controllers;
-mod entities;
-mod game;
-mod ui;
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args();
+    args.position(|arg| arg == name)?;
+    args.next()
+}
 
 fn main() {
-    App::new()
-        .add_plugins(
+    let mut app = App::new();
+    if let Some(seed) = arg_value("--seed").and_then(|seed| seed.parse().ok()) {
+        app.insert_resource(game::GameRng::new(Some(seed)));
+    }
+    if let Some(mode) = arg_value("--mode").and_then(|mode| game::GameMode::from_key(&mode)) {
+        app.insert_resource(mode);
+    }
+    let benchmark = arg_value("--benchmark").and_then(|enemies| enemies.parse().ok());
+    if let Some(enemies) = benchmark {
+        app.add_plugins(benchmark::BenchmarkPlugin { enemies });
+    }
+    if benchmark.is_some() || std::env::args().any(|arg| arg == "--headless") {
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .add_plugins(headless::HeadlessPlugin);
+    } else {
+        app.add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
@@ -18,9 +37,20 @@ fn main() {
                     ..default()
                 }),
         )
-        .add_plugins(entities::EntitiesPlugin)
-        .add_plugins(controllers::ControllersPlugin)
-        .add_plugins(game::GamePlugin)
         .add_plugins(ui::UIPlugin)
-        .run();
+        .add_plugins(settings::SettingsPlugin)
+        .add_plugins(touch::TouchPlugin)
+        .add_plugins(pause::PauseMenuPlugin)
+        .add_plugins(gameover::GameOverPlugin)
+        .add_plugins(highscores::HighScoresPlugin)
+        .add_plugins(leaderboard::LeaderboardPlugin {
+            url: arg_value("--leaderboard"),
+            player: arg_value("--player").unwrap_or_else(|| "anonymous".into()),
+        });
+    }
+    app.add_plugins(GamePlugins {
+        record_to: arg_value("--record"),
+        replay_from: arg_value("--replay"),
+    })
+    .run();
 }
@@ -0,0 +1,129 @@
+use crate::controllers::{Action, ActionInput};
+use bevy::prelude::*;
+// the entries of an overlay menu, top to bottom
+pub trait MenuEntry: Copy + PartialEq + Send + Sync + 'static {
+    const ALL: &'static [Self];
+    fn label(&self) -> &'static str;
+}
+#[derive(Component)]
+pub struct MenuButton<E: MenuEntry>(pub E);
+// the highlighted entry, an index into MenuEntry::ALL
+#[derive(Default)]
+pub struct MenuSelection {
+    selected: usize,
+}
+impl MenuSelection {
+    pub fn reset(&mut self) {
+        self.selected = 0;
+    }
+    // up and down move the highlight around, confirm picks it
+    pub fn navigate<E: MenuEntry>(&mut self, input: &mut ActionInput) -> Option<E> {
+        let count = E::ALL.len();
+        if input.just_pressed(Action::MoveUp) {
+            self.selected = (self.selected + count - 1) % count;
+        }
+        if input.just_pressed(Action::MoveDown) {
+            self.selected = (self.selected + 1) % count;
+        }
+        if input.just_pressed(Action::Confirm) {
+            input.consume(Action::Confirm);
+            return E::ALL.get(self.selected).copied();
+        }
+        None
+    }
+    // hovering selects like the keys do, so the highlight never points at two entries
+    pub fn click<'a, E: MenuEntry>(
+        &mut self,
+        interactions: impl IntoIterator<Item = (&'a Interaction, &'a MenuButton<E>)>,
+    ) -> Option<E> {
+        let mut chosen = None;
+        for (interaction, button) in interactions {
+            let Some(index) = E::ALL.iter().position(|entry| *entry == button.0) else {
+                continue;
+            };
+            match interaction {
+                Interaction::Hovered => self.selected = index,
+                Interaction::Pressed => {
+                    self.selected = index;
+                    chosen = Some(button.0);
+                }
+                Interaction::None => {}
+            }
+        }
+        chosen
+    }
+}
+
+// a dimmed full screen overlay with the header above a column of buttons, hidden until
+// shown with `show_menu`
+pub fn spawn_menu<E: MenuEntry>(
+    commands: &mut Commands,
+    overlay: impl Bundle,
+    header: impl Bundle,
+    style: TextStyle,
+) {
+    commands
+        .spawn((
+            overlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+        ))
+        .with_children(|overlay| {
+            overlay.spawn(header);
+            for entry in E::ALL {
+                overlay
+                    .spawn((
+                        MenuButton(*entry),
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(320.),
+                                padding: UiRect::all(Val::Px(8.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(entry.label(), style.clone()));
+                    });
+            }
+        });
+}
+
+pub fn show_menu<'a, E: MenuEntry>(
+    open: bool,
+    selection: &MenuSelection,
+    overlays: impl IntoIterator<Item = Mut<'a, Visibility>>,
+    buttons: impl IntoIterator<Item = (&'a MenuButton<E>, Mut<'a, BackgroundColor>)>,
+) {
+    for mut visibility in overlays {
+        *visibility = if open {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    for (button, mut background) in buttons {
+        let selected = E::ALL.get(selection.selected) == Some(&button.0);
+        *background = if selected {
+            Color::rgba(1., 1., 1., 0.3).into()
+        } else {
+            Color::NONE.into()
+        };
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    controllers::ActionInput,
+    entities::{despawn_game_entities, GameEntity},
+    game::{toggle_pause, GameResources, GameState},
+    menu::{show_menu, spawn_menu, MenuButton, MenuEntry, MenuSelection},
+    replay::live_input,
+    settings::{update_settings_menu, SettingsMenu},
+};
+use bevy::prelude::*;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseEntry {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+impl MenuEntry for PauseEntry {
+    const ALL: &'static [PauseEntry] = &[
+        PauseEntry::Resume,
+        PauseEntry::Restart,
+        PauseEntry::Settings,
+        PauseEntry::Quit,
+    ];
+    fn label(&self) -> &'static str {
+        match self {
+            PauseEntry::Resume => "resume",
+            PauseEntry::Restart => "restart run",
+            PauseEntry::Settings => "settings",
+            PauseEntry::Quit => "quit to menu",
+        }
+    }
+}
+// sent once an entry is picked, by key, button or click
+#[derive(Event)]
+pub struct PauseMenuChoice(pub PauseEntry);
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    selection: MenuSelection,
+}
+#[derive(Component)]
+pub struct PauseOverlay;
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>()
+            .add_event::<PauseMenuChoice>()
+            .add_systems(Startup, spawn_pause_menu)
+            .add_systems(OnEnter(GameState::Pause), reset_pause_menu)
+            // the pause action resumes first, so a start button bound to both can't also
+            // confirm the selected entry
+            .add_systems(
+                Update,
+                navigate_pause_menu
+                    .after(update_settings_menu)
+                    .after(toggle_pause)
+                    .run_if(live_input),
+            )
+            .add_systems(Update, apply_pause_menu.after(navigate_pause_menu))
+            .add_systems(Update, render_pause_menu);
+    }
+}
+
+fn reset_pause_menu(mut pause_menu: ResMut<PauseMenu>) {
+    pause_menu.selection.reset();
+}
+
+fn navigate_pause_menu(
+    mut input: ActionInput,
+    mut pause_menu: ResMut<PauseMenu>,
+    mut choices: EventWriter<PauseMenuChoice>,
+    button_query: Query<(&Interaction, &MenuButton<PauseEntry>), Changed<Interaction>>,
+    settings_menu: Res<SettingsMenu>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.get() != GameState::Pause || settings_menu.open {
+        return;
+    }
+    let keys = pause_menu.selection.navigate(&mut input);
+    let click = pause_menu.selection.click(&button_query);
+    if let Some(entry) = click.or(keys) {
+        choices.send(PauseMenuChoice(entry));
+    }
+}
+
+// restarting and quitting clean up the run right here, without a trip through game over
+fn apply_pause_menu(
+    mut commands: Commands,
+    mut choices: EventReader<PauseMenuChoice>,
+    query: Query<Entity, With<GameEntity>>,
+    mut game_resources: ResMut<GameResources>,
+    mut settings_menu: ResMut<SettingsMenu>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    let Some(PauseMenuChoice(entry)) = choices.read().last() else {
+        return;
+    };
+    match entry {
+        PauseEntry::Resume => next_game_state.set(GameState::Active),
+        PauseEntry::Restart => {
+            despawn_game_entities(&mut commands, &query);
+            game_resources.reset();
+            next_game_state.set(GameState::Init);
+        }
+        PauseEntry::Settings => settings_menu.open = true,
+        PauseEntry::Quit => {
+            despawn_game_entities(&mut commands, &query);
+            game_resources.reset();
+            next_game_state.set(GameState::StartMenu);
+        }
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font_size: 24.,
+        color: Color::WHITE,
+        font: asset_server.load("fonts/bigblueterm.ttf"),
+    };
+    let title = TextBundle::from_section(
+        "paused",
+        TextStyle {
+            font_size: 40.,
+            ..style.clone()
+        },
+    );
+    spawn_menu::<PauseEntry>(&mut commands, PauseOverlay, title, style);
+}
+
+fn render_pause_menu(
+    mut overlay_query: Query<&mut Visibility, With<PauseOverlay>>,
+    mut button_query: Query<(&MenuButton<PauseEntry>, &mut BackgroundColor)>,
+    pause_menu: Res<PauseMenu>,
+    settings_menu: Res<SettingsMenu>,
+    game_state: Res<State<GameState>>,
+) {
+    let open = *game_state.get() == GameState::Pause && !settings_menu.open;
+    show_menu(
+        open,
+        &pause_menu.selection,
+        &mut overlay_query,
+        &mut button_query,
+    );
+}
@@ -0,0 +1,348 @@
+use crate::{
+    controllers::{PlayerControllerState, PlayerControllers},
+    game::{GameMode, GameResources, GameRng, GameState, PlayerSetup},
+    storage,
+};
+use bevy::{
+    ecs::schedule::ExecutorKind,
+    prelude::*,
+    time::{TimeSystem, TimeUpdateStrategy},
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{error::Error, fs, time::Duration};
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayTransition {
+    Start,
+    Pause,
+    Resume,
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct ReplayInput {
+    pub x: f32,
+    pub y: f32,
+    pub boost: f32,
+}
+impl ReplayInput {
+    fn record(state: &PlayerControllerState) -> Self {
+        let (x, y) = state.get_state();
+        Self {
+            x,
+            y,
+            boost: state.boost(),
+        }
+    }
+    fn play(&self, state: &mut PlayerControllerState) {
+        state.set_state(self.x, self.y, self.boost);
+    }
+}
+// player one's input stays inline so replays from before co-op still load
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayFrame {
+    pub delta: Duration,
+    pub x: f32,
+    pub y: f32,
+    #[serde(deserialize_with = "held_or_amount")]
+    pub boost: f32,
+    #[serde(default)]
+    pub player_two: Option<ReplayInput>,
+    pub transition: Option<ReplayTransition>,
+}
+// replays from before the analog boost stored whether it was held
+fn held_or_amount<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Boost {
+        Held(bool),
+        Amount(f32),
+    }
+    Ok(match Boost::deserialize(deserializer)? {
+        Boost::Held(true) => 1.,
+        Boost::Held(false) => 0.,
+        Boost::Amount(amount) => amount,
+    })
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Replay {
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: GameMode,
+    #[serde(default)]
+    pub setup: PlayerSetup,
+    pub frames: Vec<ReplayFrame>,
+}
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+    // fnv-1a over the serialized replay, stable across platforms and compiler versions
+    pub fn hash(&self) -> Result<u64, Box<dyn Error>> {
+        Ok(ron::to_string(self)?
+            .bytes()
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+            }))
+    }
+}
+// sent once a recorded run is over and its replay is kept, so the run can be submitted
+// with proof
+#[derive(Event)]
+pub struct RunRecorded {
+    pub seed: u64,
+    pub hash: u64,
+}
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    // every run is recorded, written to the path when one was given and kept when the run
+    // gets submitted
+    path: Option<String>,
+    replay: Replay,
+    recording: bool,
+}
+// runs that get submitted keep their replay under its hash, see `keep_replays`
+#[derive(Resource)]
+pub struct KeepReplays;
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    frame: usize,
+    started: bool,
+    // recorded on the frame that asked for it, applied on the next one
+    pending: Option<ReplayTransition>,
+    resume_strategy: Option<TimeUpdateStrategy>,
+}
+pub struct ReplayPlugin {
+    pub record_to: Option<String>,
+    pub replay_from: Option<String>,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplayRecorder {
+            path: self.record_to.clone(),
+            replay: Replay::default(),
+            recording: false,
+        })
+        .add_event::<RunRecorded>();
+        if let Some(path) = &self.replay_from {
+            match Replay::load(path) {
+                Ok(replay) => {
+                    app.insert_resource(GameRng::new(Some(replay.seed)))
+                        .insert_resource(replay.mode)
+                        .insert_resource(replay.setup)
+                        .insert_resource(ReplayPlayback {
+                            replay,
+                            frame: 0,
+                            started: false,
+                            pending: None,
+                            resume_strategy: None,
+                        });
+                }
+                Err(err) => error!("could not load replay {}: {}", path, err),
+            }
+        }
+        if self.record_to.is_some() || self.replay_from.is_some() {
+            deterministic_update(app);
+        }
+        app.add_systems(
+            First,
+            set_replay_timestep
+                .before(TimeSystem)
+                .run_if(resource_exists::<ReplayPlayback>()),
+        )
+        .add_systems(
+            PreUpdate,
+            play_replay.run_if(resource_exists::<ReplayPlayback>()),
+        )
+        .add_systems(PostUpdate, record_replay);
+    }
+}
+
+// the multi threaded executor may reorder conflicting systems between runs, a run that is
+// saved or played back has to come out the same way every time
+pub fn deterministic_update(app: &mut App) {
+    app.edit_schedule(Update, |schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    });
+}
+
+// the hash sent with a run only proves something if the run can be played back from it
+pub fn keep_replays(app: &mut App) {
+    deterministic_update(app);
+    app.insert_resource(KeepReplays);
+}
+
+// where a kept replay is stored, next to the high scores
+pub fn kept_replay_name(hash: u64) -> String {
+    format!("replays/{:016x}", hash)
+}
+
+pub fn live_input(playback: Option<Res<ReplayPlayback>>) -> bool {
+    playback.is_none()
+}
+
+fn set_replay_timestep(
+    mut playback: ResMut<ReplayPlayback>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+) {
+    if !playback.started {
+        return;
+    }
+    if let Some(delta) = playback.replay.frames.get(playback.frame).map(|f| f.delta) {
+        let previous = std::mem::replace(&mut *strategy, TimeUpdateStrategy::ManualDuration(delta));
+        if playback.resume_strategy.is_none() {
+            playback.resume_strategy = Some(previous);
+        }
+    }
+}
+
+fn play_replay(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    mut controllers: ResMut<PlayerControllers>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut game_resources: ResMut<GameResources>,
+) {
+    if !playback.started {
+        if *game_state.get() != GameState::StartMenu {
+            return;
+        }
+        playback.started = true;
+    }
+    match playback.replay.frames.get(playback.frame).cloned() {
+        Some(frame) => {
+            let player_one = ReplayInput {
+                x: frame.x,
+                y: frame.y,
+                boost: frame.boost,
+            };
+            player_one.play(controllers.get_mut(0));
+            frame
+                .player_two
+                .unwrap_or_default()
+                .play(controllers.get_mut(1));
+            // the recorder sees a transition in the frame whose Update asked for it, which
+            // still ran in the old state, so it only takes effect here a frame later
+            match std::mem::replace(&mut playback.pending, frame.transition) {
+                Some(ReplayTransition::Start) => {
+                    next_game_state.set(GameState::Init);
+                    game_resources.reset();
+                }
+                Some(ReplayTransition::Pause) => next_game_state.set(GameState::Pause),
+                Some(ReplayTransition::Resume) => next_game_state.set(GameState::Active),
+                None => {}
+            }
+            playback.frame += 1;
+        }
+        None => {
+            info!("replay finished after {} frames", playback.frame);
+            *controllers = PlayerControllers::default();
+            if let Some(resume_strategy) = playback.resume_strategy.take() {
+                *strategy = resume_strategy;
+            }
+            commands.remove_resource::<ReplayPlayback>();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn record_replay(
+    mut recorder: ResMut<ReplayRecorder>,
+    controllers: Res<PlayerControllers>,
+    game_state: Res<State<GameState>>,
+    next_game_state: Res<NextState<GameState>>,
+    rng: Res<GameRng>,
+    mode: Res<GameMode>,
+    setup: Res<PlayerSetup>,
+    time: Res<Time>,
+    keep: Option<Res<KeepReplays>>,
+    mut recorded: EventWriter<RunRecorded>,
+) {
+    let state = *game_state.get();
+    let transition = match (state, next_game_state.0) {
+        // restarting from the pause menu or retrying after game over starts it over
+        (GameState::StartMenu | GameState::Pause | GameState::GameOver, Some(GameState::Init)) => {
+            Some(ReplayTransition::Start)
+        }
+        (GameState::Active, Some(GameState::Pause)) => Some(ReplayTransition::Pause),
+        (GameState::Pause, Some(GameState::Active)) => Some(ReplayTransition::Resume),
+        _ => None,
+    };
+    if transition == Some(ReplayTransition::Start) {
+        recorder.recording = true;
+        recorder.replay.frames.clear();
+    }
+    // a run quit from the pause menu is dropped, not saved or submitted
+    if (state, next_game_state.0) == (GameState::Pause, Some(GameState::StartMenu)) {
+        recorder.recording = false;
+    }
+    if !recorder.recording {
+        return;
+    }
+    let player_one = ReplayInput::record(controllers.get(0));
+    recorder.replay.frames.push(ReplayFrame {
+        delta: time.delta(),
+        x: player_one.x,
+        y: player_one.y,
+        boost: player_one.boost,
+        player_two: (setup.count() > 1).then(|| ReplayInput::record(controllers.get(1))),
+        transition,
+    });
+    if state == GameState::GameOver {
+        recorder.recording = false;
+        recorder.replay.seed = rng.seed();
+        recorder.replay.mode = *mode;
+        recorder.replay.setup = *setup;
+        if keep.is_some() {
+            match keep_replay(&recorder.replay) {
+                Ok(hash) => recorded.send(RunRecorded {
+                    seed: recorder.replay.seed,
+                    hash,
+                }),
+                Err(err) => error!("could not keep replay: {}", err),
+            }
+        }
+        if let Some(path) = &recorder.path {
+            match recorder.replay.save(path) {
+                Ok(()) => info!("saved replay to {}", path),
+                Err(err) => error!("could not save replay {}: {}", path, err),
+            }
+        }
+    }
+}
+
+// a run whose replay couldn't be stored is never submitted
+fn keep_replay(replay: &Replay) -> Result<u64, Box<dyn Error>> {
+    let hash = replay.hash()?;
+    storage::write(&kept_replay_name(hash), &ron::to_string(replay)?)?;
+    info!("kept replay {:016x}", hash);
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_boost_as_held_or_amount() {
+        let old = "(seed:7,frames:[(delta:(secs:0,nanos:16666666),x:0.0,y:1.0,boost:true,transition:Some(Start)),(delta:(secs:0,nanos:16666666),x:0.0,y:1.0,boost:false,transition:None)])";
+        let replay: Replay = ron::from_str(old).unwrap();
+        assert_eq!(replay.frames[0].boost, 1.);
+        assert_eq!(replay.frames[1].boost, 0.);
+        let new = ron::to_string(&Replay {
+            frames: vec![ReplayFrame {
+                boost: 0.5,
+                ..replay.frames[0].clone()
+            }],
+            ..replay
+        })
+        .unwrap();
+        let replay: Replay = ron::from_str(&new).unwrap();
+        assert_eq!(replay.frames[0].boost, 0.5);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    controllers::{Action, ActionInput, ActionMap},
+    game::{select_game_mode, toggle_pause, toggle_start, GameState},
+    replay::live_input,
+};
+use bevy::prelude::*;
+// opens and closes the settings screen on the start menu, never rebindable
+pub const SETTINGS_KEY: KeyCode = KeyCode::Tab;
+pub const SETTINGS_BUTTON: GamepadButtonType = GamepadButtonType::Select;
+// one row per action, then the control scheme and the reset row
+const SCHEME_ROW: usize = Action::ALL.len();
+const RESET_ROW: usize = SCHEME_ROW + 1;
+#[derive(Resource, Default)]
+pub struct SettingsMenu {
+    pub open: bool,
+    selected: usize,
+    // the next key or button pressed gets bound to the selected action
+    listening: bool,
+}
+#[derive(Component)]
+pub struct SettingsOverlay;
+#[derive(Component)]
+pub struct SettingsText;
+#[derive(Component)]
+pub struct SettingsHint;
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsMenu>()
+            .add_systems(Startup, spawn_settings_menu)
+            // the menu swallows every press while it is open
+            .add_systems(
+                Update,
+                update_settings_menu
+                    .before(toggle_start)
+                    .before(toggle_pause)
+                    .before(select_game_mode)
+                    .run_if(live_input),
+            )
+            .add_systems(Update, render_settings_menu);
+    }
+}
+
+fn save_bindings(action_map: &ActionMap) {
+    if let Err(err) = action_map.save() {
+        error!("could not save key bindings: {}", err);
+    }
+}
+
+// reachable from the start menu and the pause menu
+pub fn update_settings_menu(
+    mut menu: ResMut<SettingsMenu>,
+    mut input: ActionInput,
+    game_state: Res<State<GameState>>,
+) {
+    if !matches!(*game_state.get(), GameState::StartMenu | GameState::Pause) {
+        menu.open = false;
+        menu.listening = false;
+        return;
+    }
+    let was_open = menu.open;
+    // the pause action backs out of a rebind instead of getting bound
+    if menu.listening && input.just_pressed(Action::Pause) {
+        menu.listening = false;
+    } else if menu.listening {
+        let action = Action::ALL[menu.selected];
+        // the settings key and button stay reserved for opening this menu
+        let key = input.just_pressed_key().filter(|key| *key != SETTINGS_KEY);
+        let button = input
+            .just_pressed_button()
+            .filter(|button| *button != SETTINGS_BUTTON);
+        let bound = if let Some(key) = key {
+            input.map.bind_key(action, key)
+        } else if let Some(button) = button {
+            input.map.bind_button(action, button)
+        } else {
+            input.consume_all();
+            return;
+        };
+        // a refused press keeps listening for another one
+        if bound {
+            menu.listening = false;
+            save_bindings(&input.map);
+        } else {
+            info!("that would leave another action unbound");
+        }
+    } else if input.key_just_pressed(SETTINGS_KEY) || input.button_just_pressed(SETTINGS_BUTTON) {
+        menu.open = !menu.open;
+    } else if !menu.open {
+        return;
+    } else if input.just_pressed(Action::Pause) {
+        menu.open = false;
+    } else if input.just_pressed(Action::MoveUp) {
+        menu.selected = (menu.selected + RESET_ROW) % (RESET_ROW + 1);
+    } else if input.just_pressed(Action::MoveDown) {
+        menu.selected = (menu.selected + 1) % (RESET_ROW + 1);
+    } else if input.just_pressed(Action::Confirm) {
+        if menu.selected == RESET_ROW {
+            *input.map = ActionMap::default();
+            save_bindings(&input.map);
+        } else if menu.selected == SCHEME_ROW {
+            input.map.scheme = input.map.scheme.toggle();
+            save_bindings(&input.map);
+        } else {
+            menu.listening = true;
+        }
+    }
+    if was_open || menu.open {
+        input.consume_all();
+    }
+}
+
+fn spawn_settings_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font_size: 20.,
+        color: Color::WHITE,
+        font: asset_server.load("fonts/bigblueterm.ttf"),
+    };
+    commands
+        .spawn((
+            SettingsOverlay,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.85).into(),
+                visibility: Visibility::Hidden,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+        ))
+        .with_children(|overlay| {
+            overlay.spawn((
+                TextBundle::from_sections([TextSection::new("", style.clone())]),
+                SettingsText,
+            ));
+        });
+    commands.spawn((
+        TextBundle::from_sections([TextSection::new(
+            format!("{:?} / {:?}: settings", SETTINGS_KEY, SETTINGS_BUTTON).to_lowercase(),
+            TextStyle {
+                font_size: 16.,
+                ..style
+            },
+        )])
+        .with_text_alignment(TextAlignment::Center)
+        // below the two lines of the mode hud
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(100.),
+            width: Val::Percent(100.),
+            ..default()
+        }),
+        SettingsHint,
+    ));
+}
+
+fn render_settings_menu(
+    mut overlay_query: Query<&mut Visibility, With<SettingsOverlay>>,
+    mut hint_query: Query<&mut Visibility, (With<SettingsHint>, Without<SettingsOverlay>)>,
+    mut texts: Query<&mut Text, With<SettingsText>>,
+    menu: Res<SettingsMenu>,
+    action_map: Res<ActionMap>,
+    game_state: Res<State<GameState>>,
+) {
+    let visible = |shown: bool| {
+        if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    };
+    for mut visibility in &mut overlay_query {
+        *visibility = visible(menu.open);
+    }
+    for mut visibility in &mut hint_query {
+        *visibility = visible(!menu.open && *game_state.get() == GameState::StartMenu);
+    }
+    if !menu.open {
+        return;
+    }
+    let mut table = String::from("settings\n\n");
+    for (row, action) in Action::ALL.iter().enumerate() {
+        let marker = if row == menu.selected { ">" } else { " " };
+        let bindings = if row == menu.selected && menu.listening {
+            "press a key or button, pause cancels".to_string()
+        } else {
+            let keys: Vec<String> = action_map
+                .keys(*action)
+                .iter()
+                .map(|key| format!("{:?}", key))
+                .collect();
+            let buttons: Vec<String> = action_map
+                .buttons(*action)
+                .iter()
+                .map(|button| format!("{:?}", button))
+                .collect();
+            format!("{:<20} {}", keys.join(" "), buttons.join(" "))
+        };
+        table += &format!("{}{:<12} {}\n", marker, action.label(), bindings);
+    }
+    let marker = |row: usize| if menu.selected == row { ">" } else { " " };
+    table += &format!(
+        "\n{}{:<12} {}\n",
+        marker(SCHEME_ROW),
+        "controls",
+        action_map.scheme.label()
+    );
+    table += &format!("{}reset to defaults\n", marker(RESET_ROW));
+    for mut text in &mut texts {
+        text.sections[0].value = table.clone();
+    }
+}
@@ -0,0 +1,86 @@
+// small named text files that outlive the game, like high score tables and settings
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::*;
+#[cfg(target_arch = "wasm32")]
+pub use web::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use directories::ProjectDirs;
+    use std::{
+        error::Error,
+        fs, io,
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+    fn dirs() -> Result<ProjectDirs, Box<dyn Error>> {
+        ProjectDirs::from("", "", "stupid-spooder-game")
+            .ok_or_else(|| "no home directory on this platform".into())
+    }
+    fn read_from(path: PathBuf) -> Result<Option<String>, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+    fn write_to(path: PathBuf, contents: &str) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+    fn file(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{}.ron", name))
+    }
+    pub fn read(name: &str) -> Result<Option<String>, Box<dyn Error>> {
+        read_from(file(dirs()?.data_dir(), name))
+    }
+    pub fn write(name: &str, contents: &str) -> Result<(), Box<dyn Error>> {
+        write_to(file(dirs()?.data_dir(), name), contents)
+    }
+    // settings the player edits go with the other config files, not with the game's data
+    pub fn read_config(name: &str) -> Result<Option<String>, Box<dyn Error>> {
+        read_from(file(dirs()?.config_dir(), name))
+    }
+    pub fn write_config(name: &str, contents: &str) -> Result<(), Box<dyn Error>> {
+        write_to(file(dirs()?.config_dir(), name), contents)
+    }
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+// the browser build keeps everything in localStorage, SystemTime isn't available there either
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use std::error::Error;
+    use web_sys::Storage;
+    fn key(name: &str) -> String {
+        format!("stupid-spooder-game.{}", name)
+    }
+    fn local_storage() -> Result<Storage, Box<dyn Error>> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| "localStorage is unavailable".into())
+    }
+    pub fn read(name: &str) -> Result<Option<String>, Box<dyn Error>> {
+        local_storage()?
+            .get_item(&key(name))
+            .map_err(|_| "could not read from localStorage".into())
+    }
+    pub fn write(name: &str, contents: &str) -> Result<(), Box<dyn Error>> {
+        local_storage()?
+            .set_item(&key(name), contents)
+            .map_err(|_| "could not write to localStorage".into())
+    }
+    // localStorage has no separate place for config
+    pub use self::{read as read_config, write as write_config};
+    pub fn now() -> u64 {
+        (js_sys::Date::now() / 1000.) as u64
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    controllers::{mouse_follow_controller, player_controller, ActionMap, PlayerControllers},
+    game::{GameResources, GameState},
+    replay::live_input,
+    settings::SettingsMenu,
+};
+use bevy::{input::touch::Touch, prelude::*, window::PrimaryWindow};
+// how far a finger has to drag the stick for full speed, in logical pixels
+const JOYSTICK_RADIUS: f32 = 60.;
+const JOYSTICK_KNOB_SIZE: f32 = 48.;
+const BOOST_BUTTON_SIZE: f32 = 120.;
+const PAUSE_BUTTON_SIZE: f32 = 64.;
+const BUTTON_MARGIN: f32 = 32.;
+// fingers that moved less than this between touching down and lifting tapped
+const TAP_DISTANCE: f32 = 16.;
+// touching the left half of the screen puts the stick down where the finger is, holding
+// anywhere on the right half boosts and the button in the top right corner pauses
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    // switched on by the first touch and off again by the keyboard
+    pub active: bool,
+    joystick: Option<u64>,
+    stick: Vec2,
+    boost: bool,
+}
+#[derive(Component)]
+pub struct TouchOverlay;
+#[derive(Component)]
+pub enum Joystick {
+    Base,
+    Knob,
+}
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchControls>()
+            .add_systems(Startup, spawn_touch_overlay)
+            // on top of whatever the keyboard and gamepads asked for this frame
+            .add_systems(
+                PreUpdate,
+                touch_controller
+                    .after(player_controller)
+                    .after(mouse_follow_controller)
+                    .run_if(live_input),
+            )
+            .add_systems(Update, tap_start_and_pause.run_if(live_input))
+            .add_systems(Update, render_touch_overlay);
+    }
+}
+
+enum TouchArea {
+    Joystick,
+    Boost,
+    Pause,
+}
+
+fn touch_area(position: Vec2, window: &Window) -> TouchArea {
+    let pause = Rect::new(
+        window.width() - BUTTON_MARGIN - PAUSE_BUTTON_SIZE,
+        BUTTON_MARGIN,
+        window.width() - BUTTON_MARGIN,
+        BUTTON_MARGIN + PAUSE_BUTTON_SIZE,
+    );
+    if pause.contains(position) {
+        TouchArea::Pause
+    } else if position.x < window.width() / 2. {
+        TouchArea::Joystick
+    } else {
+        TouchArea::Boost
+    }
+}
+
+fn is_tap(touch: &Touch) -> bool {
+    touch.distance().length() < TAP_DISTANCE
+}
+
+fn touch_controller(
+    mut touch_controls: ResMut<TouchControls>,
+    mut controllers: ResMut<PlayerControllers>,
+    touches: Res<Touches>,
+    keys: Res<Input<KeyCode>>,
+    action_map: Res<ActionMap>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    if touches.iter_just_pressed().next().is_some() {
+        touch_controls.active = true;
+    } else if keys.get_just_pressed().next().is_some() {
+        touch_controls.active = false;
+    }
+    if !touch_controls.active {
+        return;
+    }
+    // the stick follows one finger until it lifts
+    if let Some(id) = touch_controls.joystick {
+        if touches.get_pressed(id).is_none() {
+            touch_controls.joystick = None;
+        }
+    }
+    if touch_controls.joystick.is_none() {
+        touch_controls.joystick = touches
+            .iter_just_pressed()
+            .find(|touch| matches!(touch_area(touch.position(), window), TouchArea::Joystick))
+            .map(|touch| touch.id());
+    }
+    // screen coordinates grow downwards
+    touch_controls.stick = touch_controls
+        .joystick
+        .and_then(|id| touches.get_pressed(id))
+        .map_or(Vec2::ZERO, |touch| {
+            let offset = touch.distance() / JOYSTICK_RADIUS;
+            Vec2::new(offset.x, -offset.y).clamp_length_max(1.)
+        });
+    touch_controls.boost = touches
+        .iter()
+        .any(|touch| matches!(touch_area(touch.start_position(), window), TouchArea::Boost));
+    // a held key still decides its axis, like with a gamepad stick
+    if touch_controls.joystick.is_some() || touch_controls.boost {
+        let processing = &action_map.processing;
+        let boost = if touch_controls.boost { 1. } else { 0. };
+        controllers
+            .get_mut(0)
+            .resolve(processing.stick(touch_controls.stick), boost, processing);
+    }
+}
+
+// a tap starts a run from the start menu and the pause button toggles pause, the pause
+// menu's own buttons take taps like clicks
+fn tap_start_and_pause(
+    touches: Res<Touches>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    game_state: Res<State<GameState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut game_resources: ResMut<GameResources>,
+    settings_menu: Res<SettingsMenu>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    for touch in touches.iter_just_released().filter(|touch| is_tap(touch)) {
+        match (
+            *game_state.get(),
+            touch_area(touch.start_position(), window),
+        ) {
+            (GameState::StartMenu, _) if !settings_menu.open => {
+                next_game_state.set(GameState::Init);
+                game_resources.reset();
+            }
+            (GameState::Active, TouchArea::Pause) => next_game_state.set(GameState::Pause),
+            (GameState::Pause, TouchArea::Pause) => next_game_state.set(GameState::Active),
+            _ => continue,
+        }
+        return;
+    }
+}
+
+fn spawn_touch_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font_size: 16.,
+        color: Color::WHITE,
+        font: asset_server.load("fonts/bigblueterm.ttf"),
+    };
+    let button = |size: f32| Style {
+        position_type: PositionType::Absolute,
+        width: Val::Px(size),
+        height: Val::Px(size),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let background = Color::rgba(1., 1., 1., 0.2).into();
+    commands
+        .spawn((
+            TouchOverlay,
+            NodeBundle {
+                style: Style {
+                    right: Val::Px(BUTTON_MARGIN),
+                    bottom: Val::Px(BUTTON_MARGIN),
+                    ..button(BOOST_BUTTON_SIZE)
+                },
+                background_color: background,
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section("boost", style.clone()));
+        });
+    commands
+        .spawn((
+            TouchOverlay,
+            NodeBundle {
+                style: Style {
+                    right: Val::Px(BUTTON_MARGIN),
+                    top: Val::Px(BUTTON_MARGIN),
+                    ..button(PAUSE_BUTTON_SIZE)
+                },
+                background_color: background,
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ))
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section("ii", style));
+        });
+    commands.spawn((
+        Joystick::Base,
+        NodeBundle {
+            style: button(JOYSTICK_RADIUS * 2.),
+            background_color: background,
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+    ));
+    commands.spawn((
+        Joystick::Knob,
+        NodeBundle {
+            style: button(JOYSTICK_KNOB_SIZE),
+            background_color: Color::rgba(1., 1., 1., 0.5).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(1),
+            ..default()
+        },
+    ));
+}
+
+fn render_touch_overlay(
+    mut overlay_query: Query<&mut Visibility, With<TouchOverlay>>,
+    mut joystick_query: Query<(&mut Style, &mut Visibility, &Joystick), Without<TouchOverlay>>,
+    touch_controls: Res<TouchControls>,
+    touches: Res<Touches>,
+    game_state: Res<State<GameState>>,
+) {
+    let playing =
+        touch_controls.active && matches!(*game_state.get(), GameState::Active | GameState::Pause);
+    let visible = |shown: bool| {
+        if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    };
+    for mut visibility in &mut overlay_query {
+        *visibility = visible(playing);
+    }
+    let joystick = touch_controls
+        .joystick
+        .and_then(|id| touches.get_pressed(id))
+        .filter(|_| playing);
+    let origin = joystick.map_or(Vec2::ZERO, |touch| touch.start_position());
+    for (mut style, mut visibility, part) in &mut joystick_query {
+        // the knob sits where the stick points, never past the edge of the base
+        let (center, size) = match part {
+            Joystick::Base => (origin, JOYSTICK_RADIUS * 2.),
+            Joystick::Knob => (
+                origin
+                    + Vec2::new(touch_controls.stick.x, -touch_controls.stick.y) * JOYSTICK_RADIUS,
+                JOYSTICK_KNOB_SIZE,
+            ),
+        };
+        *visibility = visible(joystick.is_some());
+        style.left = Val::Px(center.x - size / 2.);
+        style.top = Val::Px(center.y - size / 2.);
+    }
+}
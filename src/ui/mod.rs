@@ -1,7 +1,11 @@
 use crate::{
-    controllers::PlayerControllerState,
-    entities::Background,
-    game::{GameResources, GameState},
+    bosses::Boss,
+    controllers::PlayerControllers,
+    definitions::BossDefinitions,
+    entities::{Background, GameEntity},
+    game::{GameMode, GameResources, GameState, PlayerSetup},
+    replay::live_input,
+    settings::SettingsMenu,
 };
 use bevy::{
     app::{App, Plugin, Update},
@@ -10,8 +14,8 @@ use bevy::{
     input::mouse::MouseButtonInput,
     math::{Vec2, Vec3},
     prelude::{
-        default, Commands, Component, Deref, DerefMut, Entity, EventReader, NextState, Query, Res,
-        ResMut, State, With,
+        default, Commands, Component, Deref, DerefMut, Entity, EventReader, IntoSystemConfigs,
+        NextState, Query, Res, ResMut, State, With,
     },
     render::view::Visibility,
     sprite::{
@@ -26,10 +30,17 @@ use std::ops::Mul;
 pub struct Song {
     title: String,
 }
+// both point at the energy pool they show
 #[derive(Component)]
-pub struct EnergyBarFire;
+pub struct EnergyBarFire {
+    pool: usize,
+}
 #[derive(Component)]
-pub struct EnergyBar;
+pub struct EnergyBar {
+    pool: usize,
+}
+// with one bar per player they move this far left and right of the middle
+const ENERGY_BAR_OFFSET: f32 = 240.;
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(Timer);
 #[derive(Component)]
@@ -49,34 +60,41 @@ impl Plugin for UIPlugin {
             .add_systems(Update, update_energy_bar)
             .add_systems(Update, manage_songs)
             .add_systems(Update, animate_sprites)
-            .add_systems(Update, manage_start_button)
+            .add_systems(Update, manage_start_button.run_if(live_input))
             .add_systems(Update, show_start_menu_ui)
             .add_systems(Update, update_energy_bar_fire);
     }
 }
 
 pub fn update_energy_bar_fire(
-    mut query: Query<
-        (
-            &mut TextureAtlasSprite,
-            &mut Visibility,
-            &mut AnimationTimer,
-        ),
-        With<EnergyBarFire>,
-    >,
-    controller_state: Res<PlayerControllerState>,
+    mut query: Query<(
+        &mut TextureAtlasSprite,
+        &mut Visibility,
+        &mut AnimationTimer,
+        &EnergyBarFire,
+    )>,
+    controllers: Res<PlayerControllers>,
     time: Res<Time>,
     game_state: Res<State<GameState>>,
+    mode: Res<GameMode>,
+    setup: Res<PlayerSetup>,
 ) {
     let state = *game_state.get();
-    for (mut sprite, mut visibility, mut timer) in &mut query {
+    for (mut sprite, mut visibility, mut timer, fire) in &mut query {
+        // burns while anyone eating from this pool boosts
+        let boosting = (0..setup.count()).any(|player| {
+            setup.energy_pool(player) == fire.pool && controllers.get(player).is_boosting()
+        });
         match state {
             GameState::StartMenu | GameState::GameOver => {
                 *visibility = Visibility::Hidden;
             }
+            _ if !mode.burns_energy() => {
+                *visibility = Visibility::Hidden;
+            }
             _ => {
                 *visibility = Visibility::Visible;
-                if controller_state.is_boosting() {
+                if boosting {
                     timer.tick(time.delta());
                     if timer.just_finished() {
                         sprite.index = if sprite.index == 3 {
@@ -94,19 +112,24 @@ pub fn update_energy_bar_fire(
 }
 
 pub fn update_energy_bar(
-    mut query: Query<(&mut TextureAtlasSprite, &mut Visibility), With<EnergyBar>>,
+    mut query: Query<(&mut TextureAtlasSprite, &mut Visibility, &EnergyBar)>,
     game_resources: Res<GameResources>,
     game_state: Res<State<GameState>>,
+    mode: Res<GameMode>,
 ) {
     let state = *game_state.get();
-    for (mut energy_bar, mut visibility) in &mut query {
+    for (mut energy_bar, mut visibility, bar) in &mut query {
         match state {
             GameState::StartMenu | GameState::GameOver => {
                 *visibility = Visibility::Hidden;
             }
+            // nothing to run out of in zen
+            _ if !mode.burns_energy() => {
+                *visibility = Visibility::Hidden;
+            }
             _ => {
                 *visibility = Visibility::Visible;
-                let index = match game_resources.energy {
+                let index = match game_resources.energy[bar.pool] {
                     0.0..=0.25 => 1,
                     0.25..=0.4 => 2,
                     0.4..=0.55 => 3,
@@ -121,15 +144,22 @@ pub fn update_energy_bar(
     }
 }
 
-fn new_song_tuple(name: &str, asset_server: &Res<AssetServer>) -> (Song, AudioSourceBundle) {
+const THEME_SONG: &str = "sound/theme.mp3";
+const GAME_SONG: &str = "sound/in_the_jungle.mp3";
+
+fn new_song_tuple(path: &str, asset_server: &Res<AssetServer>) -> (Song, AudioSourceBundle) {
     (
         Song {
-            title: String::from(name),
+            title: String::from(path),
         },
         AudioBundle {
-            source: asset_server.load(format!("sound/{}.mp3", &name)),
+            source: asset_server.load(path.to_string()),
             settings: PlaybackSettings {
-                volume: Volume::Relative(VolumeLevel::new(if name == "theme" { 1. } else { 0.7 })),
+                volume: Volume::Relative(VolumeLevel::new(if path == THEME_SONG {
+                    1.
+                } else {
+                    0.7
+                })),
                 mode: PlaybackMode::Loop,
                 speed: 1.,
                 spatial: false,
@@ -144,25 +174,26 @@ pub fn manage_songs(
     asset_server: Res<AssetServer>,
     game_state: Res<State<GameState>>,
     mut query: Query<(Entity, &Song)>,
+    boss_query: Query<&Boss>,
+    bosses: Res<BossDefinitions>,
 ) {
     let state = *game_state.get();
+    // a boss brings its own song and keeps it until it is caught
+    let boss_song = boss_query
+        .iter()
+        .find_map(|boss| bosses.bosses.get(boss.definition))
+        .map(|definition| definition.song.as_str());
+    let wanted = match state {
+        GameState::StartMenu => THEME_SONG,
+        _ => boss_song.unwrap_or(GAME_SONG),
+    };
     if query.is_empty() && state == GameState::StartMenu {
-        commands.spawn(new_song_tuple("theme", &asset_server));
+        commands.spawn(new_song_tuple(wanted, &asset_server));
     }
     for (entity, song) in &mut query {
-        match state {
-            GameState::StartMenu => {
-                if song.title != String::from("theme") {
-                    commands.entity(entity).despawn();
-                    commands.spawn(new_song_tuple("theme", &asset_server));
-                }
-            }
-            _ => {
-                if song.title != String::from("in_the_jungle") {
-                    commands.entity(entity).despawn();
-                    commands.spawn(new_song_tuple("in_the_jungle", &asset_server));
-                }
-            }
+        if song.title != wanted {
+            commands.entity(entity).despawn();
+            commands.spawn(new_song_tuple(wanted, &asset_server));
         }
     }
 }
@@ -174,8 +205,14 @@ pub fn manage_start_button(
     game_state: Res<State<GameState>>,
     mut next_game_state: ResMut<NextState<GameState>>,
     mut game_resources: ResMut<GameResources>,
+    settings_menu: Res<SettingsMenu>,
 ) {
-    let window = window_query.get_single().unwrap();
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    if settings_menu.open {
+        return;
+    }
     match window.cursor_position() {
         Some(cursor_pos) => {
             for (mut sprite, transform) in &mut interaction_query {
@@ -195,9 +232,7 @@ pub fn manage_start_button(
                         if click_events.read().next().is_some() {
                             if *game_state.get() == GameState::StartMenu {
                                 next_game_state.set(GameState::Init);
-                                game_resources.score = 0;
-                                game_resources.time = 0.;
-                                game_resources.energy = 1.;
+                                game_resources.reset();
                             }
                         }
                     }
@@ -322,6 +357,7 @@ pub fn spawn_ui_on_init(
     game_state: Res<State<GameState>>,
     // mut next_game_state: ResMut<NextState<GameState>>,
     mut texture_atlasses: ResMut<Assets<TextureAtlas>>,
+    setup: Res<PlayerSetup>,
 ) {
     if *game_state.get() == GameState::Init {
         let scale_f = 3.;
@@ -336,19 +372,6 @@ pub fn spawn_ui_on_init(
             None,
         );
         let energy_bar_atlas_handle = texture_atlasses.add(energy_bar_atlas);
-        commands.spawn((
-            EnergyBar,
-            SpriteSheetBundle {
-                texture_atlas: energy_bar_atlas_handle,
-                transform: Transform::from_translation(Vec3::new(0., -300., 15.)),
-                sprite: TextureAtlasSprite {
-                    index: 0,
-                    custom_size: Some(Vec2::mul(energy_bar_sprite_size, scale_f)),
-                    ..default()
-                },
-                ..default()
-            },
-        ));
         let fire_sprite_size = Vec2 { x: 69., y: 27. };
         let fire_atlas = TextureAtlas::from_grid(
             asset_server.load("sprites/energy-bar-fire.png"),
@@ -360,20 +383,43 @@ pub fn spawn_ui_on_init(
             None,
         );
         let fire_atlas_handle = texture_atlasses.add(fire_atlas);
-        commands.spawn((
-            EnergyBarFire,
-            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-            SpriteSheetBundle {
-                texture_atlas: fire_atlas_handle,
-                visibility: Visibility::Hidden,
-                transform: Transform::from_translation(Vec3::new(-4., -260., 12.)),
-                sprite: TextureAtlasSprite {
-                    index: 0,
-                    custom_size: Some(Vec2::mul(fire_sprite_size, scale_f)),
+        let pools = setup.energy_pools();
+        for pool in 0..pools {
+            let x = if pools > 1 {
+                (pool as f32 - 0.5) * 2. * ENERGY_BAR_OFFSET
+            } else {
+                0.
+            };
+            commands.spawn((
+                EnergyBar { pool },
+                GameEntity,
+                SpriteSheetBundle {
+                    texture_atlas: energy_bar_atlas_handle.clone(),
+                    transform: Transform::from_translation(Vec3::new(x, -300., 15.)),
+                    sprite: TextureAtlasSprite {
+                        index: 0,
+                        custom_size: Some(Vec2::mul(energy_bar_sprite_size, scale_f)),
+                        ..default()
+                    },
                     ..default()
                 },
-                ..default()
-            },
-        ));
+            ));
+            commands.spawn((
+                EnergyBarFire { pool },
+                GameEntity,
+                AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+                SpriteSheetBundle {
+                    texture_atlas: fire_atlas_handle.clone(),
+                    visibility: Visibility::Hidden,
+                    transform: Transform::from_translation(Vec3::new(x - 4., -260., 12.)),
+                    sprite: TextureAtlasSprite {
+                        index: 0,
+                        custom_size: Some(Vec2::mul(fire_sprite_size, scale_f)),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+        }
     }
 }
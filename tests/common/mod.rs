@@ -0,0 +1,73 @@
+use bevy::{input::InputPlugin, prelude::*};
+use stupid_spooder_game::{
+    controllers::ActionMap,
+    entities::Spider,
+    game::{GameResources, GameRng, GameState},
+    headless::HeadlessPlugin,
+    GamePlugins,
+};
+
+// an idle spider starves well within ten minutes of game time
+const MAX_FRAMES: usize = 60 * 600;
+
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub score: u32,
+    pub time: f32,
+    // frames whose Update ran with the run going
+    pub active_frames: usize,
+    // time, score, energy and where the spiders are after every one of those frames, so a
+    // run that drifts a frame and still ends the same way doesn't pass
+    pub trace: Vec<(f32, u32, f32, Vec<Vec2>)>,
+}
+
+pub fn headless_app(seed: u64, plugins: GamePlugins) -> App {
+    let mut app = App::new();
+    app.insert_resource(GameRng::new(Some(seed)))
+        .add_plugins((MinimalPlugins, InputPlugin, HeadlessPlugin))
+        .add_plugins(plugins)
+        // whatever the bindings file on this machine says
+        .insert_resource(ActionMap::default());
+    app.finish();
+    app.cleanup();
+    app
+}
+
+// plays until the run is over, `before_frame` gets the frame number to feed input
+pub fn play_to_game_over(app: &mut App, mut before_frame: impl FnMut(&mut App, usize)) -> Run {
+    let mut started = false;
+    let mut trace = Vec::new();
+    for frame in 0..MAX_FRAMES {
+        before_frame(app, frame);
+        app.update();
+        match *app.world.resource::<State<GameState>>().get() {
+            GameState::Active => {
+                started = true;
+                let spiders = app
+                    .world
+                    .query_filtered::<&Transform, With<Spider>>()
+                    .iter(&app.world)
+                    .map(|transform| transform.translation.truncate())
+                    .collect();
+                let game_resources = app.world.resource::<GameResources>();
+                trace.push((
+                    game_resources.time,
+                    game_resources.score,
+                    game_resources.energy[0],
+                    spiders,
+                ));
+            }
+            GameState::GameOver if started => {
+                let game_resources = app.world.resource::<GameResources>();
+                return Run {
+                    score: game_resources.score,
+                    time: game_resources.time,
+                    active_frames: trace.len(),
+                    trace,
+                };
+            }
+            _ => {}
+        }
+    }
+    panic!("no game over after {} frames", MAX_FRAMES);
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use common::{headless_app, play_to_game_over};
+use stupid_spooder_game::GamePlugins;
+
+#[test]
+fn headless_run_reaches_game_over() {
+    let run = play_to_game_over(&mut headless_app(7, GamePlugins::default()), |_, _| {});
+    assert!(run.time > 0.);
+    assert!(run.active_frames > 0);
+}
+
+#[test]
+fn same_seed_plays_the_same_run() {
+    let first = play_to_game_over(&mut headless_app(42, GamePlugins::default()), |_, _| {});
+    let second = play_to_game_over(&mut headless_app(42, GamePlugins::default()), |_, _| {});
+    assert_eq!(first, second);
+}
@@ -0,0 +1,55 @@
+// the stub server from the examples, its main is only for running it by hand
+#[allow(dead_code)]
+#[path = "../examples/leaderboard_stub.rs"]
+mod leaderboard_stub;
+
+use std::{net::TcpListener, sync::mpsc, thread, time::Duration};
+use stupid_spooder_game::{
+    game::GameMode,
+    leaderboard::{
+        Leaderboard, LeaderboardCallback, LeaderboardEntry, LeaderboardResult, RestLeaderboard,
+    },
+};
+
+// waits for a callback to answer, the stub never takes long
+fn wait<T: Send + 'static>(call: impl FnOnce(LeaderboardCallback<T>)) -> LeaderboardResult<T> {
+    let (sender, receiver) = mpsc::channel();
+    call(Box::new(move |result| {
+        sender.send(result).ok();
+    }));
+    receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("no answer from the leaderboard stub")
+}
+
+fn entry(player: &str, mode: GameMode, score: u32, seed: u64) -> LeaderboardEntry {
+    LeaderboardEntry {
+        player: player.into(),
+        mode,
+        score,
+        time: 12.5,
+        seed,
+        replay_hash: format!("{:016x}", seed.wrapping_mul(0x100000001b3)),
+    }
+}
+
+#[test]
+fn rest_leaderboard_talks_to_the_stub() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    thread::spawn(move || leaderboard_stub::serve(listener));
+    let leaderboard = RestLeaderboard::new(&url);
+    let first = entry("first", GameMode::Endless, 10, 1);
+    let second = entry("second", GameMode::Endless, 30, u64::MAX - 2);
+    let time_attack = entry("other mode", GameMode::TimeAttack, 20, 3);
+    for entry in [&first, &second, &time_attack] {
+        wait(|done| leaderboard.submit(entry.clone(), done)).unwrap();
+    }
+    // the seed and replay hash come back as they were sent, a seed past 2^53 included
+    let top = wait(|done| leaderboard.top(GameMode::Endless, 10, done)).unwrap();
+    assert_eq!(top, [second.clone(), first]);
+    let top = wait(|done| leaderboard.top(GameMode::Endless, 1, done)).unwrap();
+    assert_eq!(top, [second]);
+    let top = wait(|done| leaderboard.top(GameMode::TimeAttack, 10, done)).unwrap();
+    assert_eq!(top, [time_attack]);
+}
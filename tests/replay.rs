@@ -0,0 +1,76 @@
+mod common;
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ButtonState},
+    prelude::*,
+};
+use common::{headless_app, play_to_game_over};
+use stupid_spooder_game::{replay::Replay, GamePlugins};
+
+fn key(app: &mut App, key_code: KeyCode, state: ButtonState) {
+    app.world.send_event(KeyboardInput {
+        scan_code: 0,
+        key_code: Some(key_code),
+        state,
+        window: Entity::PLACEHOLDER,
+    });
+}
+
+// climbs while weaving left and right, boosts now and then and pauses once early on
+fn steer(app: &mut App, frame: usize) {
+    // held from the start menu it would pick the co-op setup instead
+    if frame == 10 {
+        key(app, KeyCode::Up, ButtonState::Pressed);
+    }
+    let (release, press) = if frame / 45 % 2 == 0 {
+        (KeyCode::Left, KeyCode::Right)
+    } else {
+        (KeyCode::Right, KeyCode::Left)
+    };
+    if frame % 45 == 0 {
+        key(app, release, ButtonState::Released);
+        key(app, press, ButtonState::Pressed);
+    }
+    match frame % 120 {
+        60 => key(app, KeyCode::ShiftLeft, ButtonState::Pressed),
+        90 => key(app, KeyCode::ShiftLeft, ButtonState::Released),
+        _ => {}
+    }
+    match frame {
+        100 | 130 => key(app, KeyCode::P, ButtonState::Pressed),
+        101 | 131 => key(app, KeyCode::P, ButtonState::Released),
+        _ => {}
+    }
+}
+
+#[test]
+fn replay_plays_back_the_recorded_run() {
+    let path = std::env::temp_dir().join(format!("spooder-replay-{}.ron", std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+    let recorded = play_to_game_over(
+        &mut headless_app(
+            1234,
+            GamePlugins {
+                record_to: Some(path.clone()),
+                ..default()
+            },
+        ),
+        steer,
+    );
+    let replay = Replay::load(&path).unwrap();
+    assert_eq!(replay.seed, 1234);
+    assert!(recorded.score > 0);
+    // a different seed on the command line loses to the one in the replay
+    let played = play_to_game_over(
+        &mut headless_app(
+            1,
+            GamePlugins {
+                replay_from: Some(path.clone()),
+                ..default()
+            },
+        ),
+        |_, _| {},
+    );
+    std::fs::remove_file(&path).ok();
+    assert_eq!(recorded, played);
+}